@@ -1,9 +1,12 @@
 // Benchmarking for todor
 
 use criterion::{criterion_group, criterion_main, Criterion};
+use std::fs;
 use std::path::Path;
 use todo_r::TodoRBuilder;
 
+static JQUERY_PATH: &str = "benches/inputs/jquery-3.3.1.js";
+
 fn bench_jquery(c: &mut Criterion) {
     c.bench_function("jquery", |b| {
         b.iter(|| {
@@ -11,12 +14,39 @@ fn bench_jquery(c: &mut Criterion) {
             let mut builder = TodoRBuilder::new();
             builder.add_override_tags(tags);
             let mut todor = builder.build().unwrap();
-            todor
-                .open_todos(Path::new("benches/inputs/jquery-3.3.1.js"))
-                .unwrap();
+            todor.open_todos(Path::new(JQUERY_PATH)).unwrap();
         })
     });
 }
 
-criterion_group!(benches, bench_jquery);
+fn bench_jquery_content(c: &mut Criterion) {
+    let content = fs::read_to_string(JQUERY_PATH).unwrap();
+    let mut builder = TodoRBuilder::new();
+    builder.add_override_tags(vec!["TODO", "FIXME"]);
+    let mut todor = builder.build().unwrap();
+
+    c.bench_function("jquery content", |b| {
+        b.iter(|| todor.find_todos(&content, "js").unwrap())
+    });
+}
+
+fn bench_jquery_trailing(c: &mut Criterion) {
+    let content = fs::read_to_string(JQUERY_PATH).unwrap();
+    let mut builder = TodoRBuilder::new();
+    builder
+        .add_override_tags(vec!["TODO", "FIXME"])
+        .set_trailing_comments(true);
+    let mut todor = builder.build().unwrap();
+
+    c.bench_function("jquery trailing comments", |b| {
+        b.iter(|| todor.find_todos(&content, "js").unwrap())
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_jquery,
+    bench_jquery_content,
+    bench_jquery_trailing
+);
 criterion_main!(benches);
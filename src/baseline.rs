@@ -0,0 +1,222 @@
+// Module for recording known TODOs so only new ones are reported
+
+use failure::Error;
+use fnv::FnvHashMap;
+use serde::{Deserialize, Serialize};
+use std::env::current_dir;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::cache::normalize_path;
+use crate::errors::TodoRError;
+use crate::todo::{root_relative_path, Todo};
+use crate::TodoR;
+
+/// Identifies a TODO in a way that does not change when the TODO moves to a different line.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Fingerprint {
+    /// Path of the file relative to the workspace root using `/` as the separator
+    pub file: String,
+    pub tag: String,
+    /// Content with whitespace runs collapsed into single spaces
+    pub content: String,
+}
+
+impl Fingerprint {
+    /// Creates the Fingerprint of todo in the file at filepath.
+    /// root is the workspace root that filepath is made relative to.
+    /// Both are taken relative to the current directory if they are not absolute.
+    pub fn new(filepath: &Path, todo: &Todo, root: &Path) -> Fingerprint {
+        let curr_dir = current_dir().unwrap_or_default();
+        Fingerprint::with_abs_root(filepath, todo, &normalize_path(&curr_dir.join(root)))
+    }
+
+    fn with_abs_root(filepath: &Path, todo: &Todo, abs_root: &Path) -> Fingerprint {
+        Fingerprint {
+            file: root_relative_path(filepath, abs_root),
+            tag: todo.tag.clone(),
+            content: todo.normalized_content(),
+        }
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} {}", self.file, self.tag, self.content)
+    }
+}
+
+/// Snapshot of known TODOs.
+///
+/// The same fingerprint can show up several times if a file has identical TODOs,
+/// so each fingerprint is stored with how many times it was seen.
+#[derive(Debug, Clone, Default)]
+pub struct Baseline {
+    counts: FnvHashMap<Fingerprint, usize>,
+}
+
+/// Layout of the baseline file.
+#[derive(Serialize, Deserialize)]
+struct BaselineSerial {
+    todos: Vec<Fingerprint>,
+}
+
+impl Baseline {
+    /// Creates a Baseline with all the TODOs tracked by todor.
+    /// Paths are stored relative to the workspace root.
+    pub fn from_todos(todor: &TodoR, root: &Path) -> Baseline {
+        let abs_root = normalize_path(&current_dir().unwrap_or_default().join(root));
+        let mut baseline = Baseline::default();
+        for ptodo in todor.iter() {
+            let fingerprint = Fingerprint::with_abs_root(ptodo.file(), ptodo.todo(), &abs_root);
+            *baseline.counts.entry(fingerprint).or_insert(0) += 1;
+        }
+
+        baseline
+    }
+
+    /// Reads a Baseline from the file at path.
+    pub fn load(path: &Path) -> Result<Baseline, Error> {
+        let reader = BufReader::new(File::open(path)?);
+        let serial: BaselineSerial =
+            serde_json::from_reader(reader).map_err(|err| TodoRError::InvalidBaselineFile {
+                message: err.to_string(),
+            })?;
+
+        let mut baseline = Baseline::default();
+        for fingerprint in serial.todos {
+            *baseline.counts.entry(fingerprint).or_insert(0) += 1;
+        }
+
+        Ok(baseline)
+    }
+
+    /// Writes the Baseline to the file at path.
+    /// Entries are sorted so the file only changes where TODOs change.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let mut todos: Vec<Fingerprint> = self
+            .counts
+            .iter()
+            .flat_map(|(fingerprint, &count)| std::iter::repeat(fingerprint.clone()).take(count))
+            .collect();
+        todos.sort();
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, &BaselineSerial { todos })?;
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    /// Returns the number of TODOs in the Baseline.
+    pub fn len(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    /// Returns true if the Baseline has no TODOs.
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Removes the TODOs in todor that are in the Baseline so only new TODOs are left.
+    ///
+    /// Returns the entries of the Baseline that did not match any TODO.
+    /// These are TODOs that have been resolved or changed since the Baseline was written.
+    pub fn remove_known_todos(&self, todor: &mut TodoR, root: &Path) -> Vec<Fingerprint> {
+        let abs_root = normalize_path(&current_dir().unwrap_or_default().join(root));
+        let mut remaining = self.counts.clone();
+
+        todor.retain_todos(|filepath, todo| {
+            let fingerprint = Fingerprint::with_abs_root(filepath, todo, &abs_root);
+            match remaining.get_mut(&fingerprint) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            }
+        });
+
+        let mut stale: Vec<Fingerprint> = remaining
+            .into_iter()
+            .flat_map(|(fingerprint, count)| std::iter::repeat(fingerprint).take(count))
+            .collect();
+        stale.sort();
+        stale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TodoRBuilder;
+
+    fn test_todor(content: &str) -> TodoR {
+        let mut todor = TodoRBuilder::new().build().unwrap();
+        let mut todo_file = todor.parser().parse_content(content, "rs").unwrap();
+        todo_file.filepath = Path::new("src").join("main.rs");
+        todor.add_todo_file(todo_file);
+        todor
+    }
+
+    #[test]
+    fn fingerprint_normalizes() {
+        let todo = Todo::new(4, "todo", "fix   this\tsoon ");
+        let fingerprint = Fingerprint::new(Path::new("ws/src/main.rs"), &todo, Path::new("ws"));
+
+        assert_eq!(fingerprint.file, "src/main.rs");
+        assert_eq!(fingerprint.tag, "TODO");
+        assert_eq!(fingerprint.content, "fix this soon");
+    }
+
+    #[test]
+    fn moved_todos_are_known() {
+        let baseline = Baseline::from_todos(&test_todor("// TODO: item\n"), Path::new("."));
+        let mut todor = test_todor("fn main() {}\n\n// TODO: item\n// TODO: new item\n");
+
+        let stale = baseline.remove_known_todos(&mut todor, Path::new("."));
+        assert!(stale.is_empty());
+        assert_eq!(todor.num_todos(), 1);
+        assert_eq!(todor.iter().next().unwrap().todo().content, "new item");
+    }
+
+    #[test]
+    fn duplicate_todos_counted() {
+        let baseline = Baseline::from_todos(&test_todor("// TODO: item\n"), Path::new("."));
+        let mut todor = test_todor("// TODO: item\n// TODO: item\n");
+
+        baseline.remove_known_todos(&mut todor, Path::new("."));
+        assert_eq!(todor.num_todos(), 1);
+    }
+
+    #[test]
+    fn stale_entries() {
+        let baseline = Baseline::from_todos(
+            &test_todor("// TODO: item\n// FIXME: done\n"),
+            Path::new("."),
+        );
+        let mut todor = test_todor("// TODO: item\n");
+
+        let stale = baseline.remove_known_todos(&mut todor, Path::new("."));
+        assert_eq!(todor.num_todos(), 0);
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].to_string(), "src/main.rs: FIXME done");
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = std::env::temp_dir().join(format!("todor-baseline-{}", std::process::id()));
+        let baseline = Baseline::from_todos(
+            &test_todor("// TODO: item\n// TODO: item\n// FIXME: other\n"),
+            Path::new("."),
+        );
+        baseline.save(&path).unwrap();
+
+        let loaded = Baseline::load(&path).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded.counts, baseline.counts);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
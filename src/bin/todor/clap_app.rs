@@ -1,4 +1,4 @@
-use clap::{App, Arg};
+use clap::{App, AppSettings, Arg};
 
 #[cfg(windows)]
 macro_rules! global_config_path {
@@ -21,7 +21,12 @@ pub fn build_cli() -> App<'static, 'static> {
         .arg(
             Arg::with_name("FILE")
                 .multiple(true)
-                .help("Sets todor to only search in provided files."),
+                .help("Sets todor to only search in provided files and directories.")
+                .long_help(
+                    "Sets todor to only search in provided files and directories. Directories are \
+                    searched recursively and skip files ignored by `.gitignore`, `.todorignore` \
+                    or `-i` the same way a workspace search does."
+                ),
         )
         .arg(
             Arg::with_name("CONFIG")
@@ -46,6 +51,16 @@ pub fn build_cli() -> App<'static, 'static> {
                 .long("no-style")
                 .help("Prints output with no ANSI colors or styles."),
         )
+        .arg(
+            Arg::with_name("IDS")
+                .long("ids")
+                .help("Shows the ID of each TODO.")
+                .long_help(
+                    "Shows the ID of each TODO after it. IDs are made from the file path, tag and \
+                    content of a TODO so they stay the same when it moves to a different line. \
+                    They are always included in the other output formats.",
+                ),
+        )
         .arg(
             Arg::with_name("TAGS")
                 .short("t")
@@ -93,6 +108,176 @@ pub fn build_cli() -> App<'static, 'static> {
                 .multiple(true)
                 .help("Files to be ignored."),
         )
+        .arg(
+            Arg::with_name("THREADS")
+                .short("j")
+                .long("threads")
+                .takes_value(true)
+                .validator(|n| {
+                    n.parse::<usize>()
+                        .map(|_| ())
+                        .map_err(|_| format!("`{}` is not a number of threads", n))
+                })
+                .help("Number of threads used to search the workspace.")
+                .long_help(
+                    "Sets the number of threads used to search files when walking the workspace. \
+                    By default, todor picks a number based on the available CPUs. Use `-j 1` to \
+                    search files one at a time. Output is sorted by path either way."
+                ),
+        )
+        .arg(
+            Arg::with_name("CACHE")
+                .long("cache")
+                .help("Caches found TODOs so unchanged files are not searched again.")
+                .long_help(
+                    "Caches TODOs found when searching the workspace in a `.todor-cache` file in \
+                    the workspace root. Files whose size and modified time have not changed since \
+                    the last run are not searched again. The cache is reset whenever the tags or \
+                    comment types change. This can also be turned on with the `cache` config \
+                    setting."
+                ),
+        )
+        .arg(
+            Arg::with_name("NO_CACHE")
+                .long("no-cache")
+                .conflicts_with("CACHE")
+                .help("Searches every file even if caching is turned on in the config."),
+        )
+        .arg(
+            Arg::with_name("SINCE")
+                .long("since")
+                .takes_value(true)
+                .value_name("REV")
+                .conflicts_with("EXT")
+                .help("Only shows TODOs on lines added or changed since a git revision.")
+                .long_help(
+                    "Only shows TODOs on lines that were added or changed in the working tree since \
+                    the git revision REV. Files that git does not track yet count as changed. \
+                    Combined with `--check`, this only fails when new TODOs are added. For \
+                    example, \n\n\
+                    \t> todor --since origin/master --check\n\n"
+                ),
+        )
+        .arg(
+            Arg::with_name("STAGED")
+                .long("staged")
+                .conflicts_with_all(&["FILE", "EXT", "REV", "BASELINE", "BLAME", "DELETE_MODE"])
+                .help("Searches the files staged in the git index instead of the working tree.")
+                .long_help(
+                    "Searches the content of files as it is staged in the git index, which is what \
+                    the next commit will have, instead of the working tree. With `--since`, only \
+                    TODOs on staged lines that changed since REV are shown. This is meant for \
+                    pre-commit hooks, which can be set up with `todor hook install`."
+                ),
+        )
+        .arg(
+            Arg::with_name("REV")
+                .long("rev")
+                .takes_value(true)
+                .value_name("REV")
+                .conflicts_with_all(&["FILE", "EXT", "SINCE", "BASELINE", "BLAME", "DELETE_MODE"])
+                .help("Searches the files in a git revision instead of the working tree.")
+                .long_help(
+                    "Searches every file in the git revision REV without checking it out, so the \
+                    working tree is left alone. The `.todor` config and `.todorignore` in the \
+                    root of the repository are used as they were at REV. For example, to list \
+                    the TODOs in a release, use \n\n\
+                    \t> todor --rev v1.2.0\n\n"
+                ),
+        )
+        .arg(
+            Arg::with_name("ROOT")
+                .long("root")
+                .takes_value(true)
+                .value_name("DIR")
+                .conflicts_with_all(&["FILE", "EXT", "REV", "STAGED"])
+                .help("Uses DIR as the workspace root instead of looking for one.")
+                .long_help(
+                    "Uses DIR as the workspace root instead of looking for one. By default, the \
+                    root is the closest directory above the current one that has a `.todor` file \
+                    or one of the `root_markers` from the config, which are `.git`, `.hg`, `.jj`, \
+                    `.svn` and `.bzr` unless changed in the global config or with `-c`."
+                ),
+        )
+        .arg(
+            Arg::with_name("RELATIVE_TO_ROOT")
+                .long("relative-to-root")
+                .conflicts_with_all(&["FILE", "EXT"])
+                .help("Shows paths relative to the workspace root instead of the current directory.")
+                .long_help(
+                    "Shows paths relative to the workspace root instead of the current directory. \
+                    Paths given to `-i` are then also taken from the root."
+                ),
+        )
+        .arg(
+            Arg::with_name("TRACKED_ONLY")
+                .long("tracked-only")
+                .conflicts_with_all(&["FILE", "EXT", "REV", "STAGED"])
+                .help("Only searches files tracked by git.")
+                .long_help(
+                    "Only searches files in the workspace that are tracked by git, so untracked \
+                    files are skipped even if `.gitignore` does not ignore them. Files in \
+                    submodules are skipped unless `--recurse-submodules` is used. `-i` and \
+                    `.todorignore` still apply."
+                ),
+        )
+        .arg(
+            Arg::with_name("RECURSE_SUBMODULES")
+                .long("recurse-submodules")
+                .requires("TRACKED_ONLY")
+                .help("Also searches files tracked in git submodules with `--tracked-only`."),
+        )
+        .arg(
+            Arg::with_name("BASELINE")
+                .long("baseline")
+                .conflicts_with_all(&["FILE", "EXT"])
+                .help("Only shows TODOs that are not in the `.todor-baseline` file.")
+                .long_help(
+                    "Only shows TODOs that are not in the `.todor-baseline` file in the workspace \
+                    root. TODOs are matched by their file, tag and content so moving a TODO to a \
+                    different line does not make it new. Combined with `--check`, this only fails \
+                    when TODOs are added. The baseline is written with `todor baseline write`."
+                ),
+        )
+        .arg(
+            Arg::with_name("WARN_STALE")
+                .long("warn-stale")
+                .requires("BASELINE")
+                .help("Warns about baseline TODOs that no longer exist."),
+        )
+        .arg(
+            Arg::with_name("BLAME")
+                .long("blame")
+                .conflicts_with("EXT")
+                .help("Shows who last changed each TODO using git blame.")
+                .long_help(
+                    "Runs git blame on the line each TODO starts on and shows its author, date \
+                    and commit. This information is also added to the `json`, `prettyjson` and \
+                    `csv` formats. Files that are not tracked by git are shown without it."
+                ),
+        )
+        .arg(
+            Arg::with_name("WATCH")
+                .short("w")
+                .long("watch")
+                .conflicts_with_all(&[
+                    "FILE",
+                    "EXT",
+                    "DELETE_MODE",
+                    "CHECK",
+                    "SINCE",
+                    "BASELINE",
+                    "REV",
+                    "STAGED",
+                ])
+                .help("Keeps running and reports TODOs again when files change.")
+                .long_help(
+                    "Keeps running after searching the workspace and searches files again when \
+                    they change. The TODO list is redrawn after every change. When the output \
+                    format is `json` or `prettyjson`, TODOs are instead written as newline \
+                    delimited JSON events with an `event` field of `add` or `remove`."
+                ),
+        )
         .arg(
             Arg::with_name("VERBOSE")
                 .short("v")
@@ -115,6 +300,13 @@ pub fn build_cli() -> App<'static, 'static> {
                     "markdown",
                     "usermarkdown",
                     "csv",
+                    "tsv",
+                    "sarif",
+                    "github",
+                    "gitlab",
+                    "junit",
+                    "checkstyle",
+                    "template",
                     "default",
                 ])
                 .help("Outputs in specified format.")
@@ -124,10 +316,52 @@ pub fn build_cli() -> App<'static, 'static> {
                     prettyjson: nicely formatted JSON\n\
                     markdown: Markdown tables with a table for each tag type\n\
                     usermarkdown: Markdown tables for each user\n\
-                    csv: Comma separated values table\n\
+                    csv: Comma separated values table following RFC 4180\n\
+                    tsv: Tab separated values table\n\
+                    sarif: SARIF 2.1.0 log for code scanning tools. The level of each tag is set \
+                    with the `severity` config setting\n\
+                    github: GitHub Actions workflow commands that annotate the lines of TODOs\n\
+                    gitlab: GitLab Code Quality report\n\
+                    junit: JUnit XML report where TODOs with an `error` severity fail and the \
+                    rest are skipped\n\
+                    checkstyle: Checkstyle XML report\n\
+                    template: each TODO rendered with the template set by --template\n\
                     default: regular output with no ANSI colors for "
                 ),
         )
+        .arg(
+            Arg::with_name("COLUMNS")
+                .long("columns")
+                .takes_value(true)
+                .value_name("COLUMNS")
+                .use_delimiter(true)
+                .requires("FORMAT")
+                .help("Chooses and orders the columns of the csv and tsv formats.")
+                .long_help(
+                    "Chooses and orders the columns of the csv and tsv formats, like \
+                    `--columns file,line,tag,users,text`. The columns are file, line, end_line, \
+                    tag, text, users, id, author, author_email, commit and date. Blame columns \
+                    are empty for TODOs without blame information."
+                ),
+        )
+        .arg(
+            Arg::with_name("TEMPLATE")
+                .long("template")
+                .takes_value(true)
+                .value_name("TEMPLATE")
+                .required_if("FORMAT", "template")
+                .help("Sets the template used by the template format.")
+                .long_help(
+                    "Sets the template used by `-f template`, like \
+                    `--template '{file}:{line}: [{tag}] {text}'`. It can also be the name of a \
+                    template under `formats` in the config, which can add headers and footers \
+                    for each file. Fields are file, line, end_line, tag, text, users, id, author, \
+                    author_email, commit and date. Filters like `{text|json}` escape fields and \
+                    can be upper, lower, json, xml, csv, tsv or shell. `{?users}...{/users}` is \
+                    only shown for TODOs with users and `{!users}...{/users}` only for TODOs \
+                    without them."
+                ),
+        )
         .arg(
             Arg::with_name("DELETE_MODE")
                 .short("d")
@@ -152,6 +386,137 @@ pub fn build_cli() -> App<'static, 'static> {
                     will take the piped output from cat and read using the .rs comment styles."
                 ),
         )
+        .subcommand(
+            App::new("baseline")
+                .about("Manages the `.todor-baseline` file of known TODOs.")
+                .author("Lavi Blumberg <lavifb@gmail.com>")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    App::new("write")
+                        .about("Records all current TODOs in the workspace to `.todor-baseline`.")
+                        .author("Lavi Blumberg <lavifb@gmail.com>"),
+                ),
+        )
+        .subcommand(
+            App::new("hook")
+                .about("Manages git hooks that run todor.")
+                .author("Lavi Blumberg <lavifb@gmail.com>")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    App::new("install")
+                        .about("Writes a git pre-commit hook that blocks commits with TODOs.")
+                        .author("Lavi Blumberg <lavifb@gmail.com>")
+                        .long_about(
+                            "Writes a git pre-commit hook that runs `todor --staged --check` so \
+                            commits fail when the staged files have TODOs. The tags and users \
+                            that block a commit can be limited with `--tag` and `--user`.",
+                        )
+                        .arg(
+                            Arg::with_name("TAGS")
+                                .short("t")
+                                .long("tag")
+                                .takes_value(true)
+                                .multiple(true)
+                                .help("Only blocks commits with these tags, like `fixme`."),
+                        )
+                        .arg(
+                            Arg::with_name("USER")
+                                .short("u")
+                                .long("user")
+                                .takes_value(true)
+                                .multiple(true)
+                                .help("Only blocks commits with TODOs tagged with these users."),
+                        )
+                        .arg(
+                            Arg::with_name("NEW_ONLY")
+                                .long("new-only")
+                                .help("Only blocks TODOs on lines changed since HEAD."),
+                        )
+                        .arg(
+                            Arg::with_name("FORCE")
+                                .long("force")
+                                .help("Replaces an existing pre-commit hook."),
+                        ),
+                ),
+        )
+        .subcommand(
+            App::new("show")
+                .about("Shows the TODO with the given ID.")
+                .author("Lavi Blumberg <lavifb@gmail.com>")
+                .arg(
+                    Arg::with_name("ID")
+                        .required(true)
+                        .help("ID of the TODO. Any prefix that matches only one TODO works."),
+                ),
+        )
+        .subcommand(
+            App::new("rm")
+                .about("Deletes the comment with the TODO with the given ID.")
+                .author("Lavi Blumberg <lavifb@gmail.com>")
+                .arg(
+                    Arg::with_name("ID")
+                        .required(true)
+                        .help("ID of the TODO. Any prefix that matches only one TODO works."),
+                ),
+        )
+        .subcommand(
+            App::new("diff")
+                .about("Lists the TODOs that changed between two reports saved with `-f json`.")
+                .author("Lavi Blumberg <lavifb@gmail.com>")
+                .arg(
+                    Arg::with_name("OLD")
+                        .required(true)
+                        .help("Report from before the changes."),
+                )
+                .arg(
+                    Arg::with_name("NEW")
+                        .required(true)
+                        .help("Report from after the changes."),
+                )
+                .arg(
+                    Arg::with_name("FORMAT")
+                        .short("f")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["default", "markdown", "json"])
+                        .help("Outputs in specified format."),
+                ),
+        )
+        .subcommand(
+            App::new("history")
+                .about("Counts TODOs by tag and user in commits across the git history.")
+                .author("Lavi Blumberg <lavifb@gmail.com>")
+                .long_about(
+                    "Counts TODOs by tag and user in commits across the git history. The last \
+                    commit of every step on the current branch is read straight from git without \
+                    checking it out. The `.todor` config and `.todorignore` in the root of the \
+                    repository are used as they were at each commit.",
+                )
+                .arg(
+                    Arg::with_name("SINCE")
+                        .long("since")
+                        .takes_value(true)
+                        .value_name("DATE")
+                        .help("Only uses commits after DATE, such as `6.months` or `2019-01-01`."),
+                )
+                .arg(
+                    Arg::with_name("STEP")
+                        .long("step")
+                        .takes_value(true)
+                        .possible_values(&["daily", "weekly", "monthly"])
+                        .default_value("weekly")
+                        .help("Time between sampled commits."),
+                )
+                .arg(
+                    Arg::with_name("FORMAT")
+                        .short("f")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["csv", "json"])
+                        .default_value("csv")
+                        .help("Outputs in specified format."),
+                ),
+        )
         .subcommand(
             App::new("init")
                 .about("Creates .todor config file and defines a todor workspace.")
@@ -0,0 +1,73 @@
+use clap::ArgMatches;
+use failure::{format_err, Error};
+use log::info;
+use std::fs;
+
+use todo_r::git::hooks_dir;
+
+/// Writes a git pre-commit hook that runs todor on the staged files.
+pub fn run_hook_install(matches: &ArgMatches) -> Result<i32, Error> {
+    let hooks_dir = hooks_dir()?;
+    let hook_path = hooks_dir.join("pre-commit");
+    if hook_path.exists() && !matches.is_present("FORCE") {
+        return Err(format_err!(
+            "`{}` already exists. Use --force to replace it",
+            hook_path.display()
+        ));
+    }
+
+    fs::create_dir_all(&hooks_dir)?;
+    fs::write(&hook_path, pre_commit_script(matches))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    info!("wrote pre-commit hook to `{}`", hook_path.display());
+    println!("Installed pre-commit hook");
+    Ok(0)
+}
+
+/// Builds the shell script for the pre-commit hook.
+fn pre_commit_script(matches: &ArgMatches) -> String {
+    let mut args = String::new();
+    if let Some(tags) = matches.values_of("TAGS") {
+        args.push_str(" -T");
+        for tag in tags {
+            args.push(' ');
+            args.push_str(&shell_quote(tag));
+        }
+    }
+    if let Some(users) = matches.values_of("USER") {
+        args.push_str(" -u");
+        for user in users {
+            args.push(' ');
+            args.push_str(&shell_quote(user));
+        }
+    }
+
+    let mut command = String::new();
+    if matches.is_present("NEW_ONLY") {
+        // before the first commit there is no HEAD and every staged line is new
+        command.push_str(&format!(
+            "if git rev-parse --verify -q HEAD >/dev/null; then\n    \
+             exec todor --staged --check --since HEAD{}\n\
+             fi\n",
+            args
+        ));
+    }
+    command.push_str(&format!("exec todor --staged --check{}", args));
+
+    format!(
+        "#!/bin/sh\n\
+         # Written by `todor hook install`. Blocks commits that have TODOs in the staged files.\n\
+         {}\n",
+        command
+    )
+}
+
+/// Quotes s so the shell passes it as a single argument.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
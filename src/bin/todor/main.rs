@@ -2,30 +2,44 @@
 
 mod clap_app;
 mod global_config;
+mod hook;
 mod logger;
 mod select;
 mod walk;
+mod watch;
 
 use atty;
 use clap::ArgMatches;
 // use env_logger;
 use failure::{format_err, Error};
 use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
 use log::*;
-use std::env::current_dir;
+use std::env::{current_dir, set_current_dir};
 use std::fs::File;
-use std::io::{stdin, Read};
-use std::path::Path;
+use std::io::{self, stdin, BufReader, Read};
+use std::path::{Path, PathBuf};
 
-use todo_r::format::ReportFormat;
-use todo_r::todo::Todo;
-use todo_r::TodoRBuilder;
+use todo_r::baseline::Baseline;
+use todo_r::diff::{read_report, TodoDiff};
+use todo_r::format::{Column, ReportFormat};
+use todo_r::git::{ChangedLines, GitTree, TrackedFiles};
+use todo_r::history::{
+    count_todos_at, sample_commits, write_history_csv, write_history_json, HistoryStep,
+};
+use todo_r::todo::{todo_id, Todo};
+use todo_r::{TodoR, TodoRBuilder};
 
 use self::clap_app::build_cli;
 use self::global_config::load_global_config;
+use self::hook::run_hook_install;
 use self::logger::init_logger;
 use self::select::run_delete;
-use self::walk::build_walker;
+use self::walk::{
+    add_todor_file_ignores, build_dir_walker, build_walker, find_root, open_todos_parallel,
+    BASELINE_FILE_NAME,
+};
+use self::watch::run_watch;
 
 /// Parses command line arguments and use TodoR to find TODO comments.
 fn main() {
@@ -39,7 +53,16 @@ fn main() {
     let exit_code = if matches.subcommand_matches("init").is_some() {
         run_init()
     } else {
-        match run(&matches) {
+        let result = match matches.subcommand() {
+            ("diff", Some(diff_matches)) => run_diff(diff_matches),
+            ("hook", Some(hook_matches)) => match hook_matches.subcommand() {
+                ("install", Some(install_matches)) => run_hook_install(install_matches),
+                _ => Ok(0),
+            },
+            _ => run(&matches),
+        };
+
+        match result {
             Ok(code) => code,
             Err(err) => {
                 error!("{}", err);
@@ -52,6 +75,13 @@ fn main() {
 }
 
 fn run(matches: &ArgMatches) -> Result<i32, Error> {
+    if let (true, Some(subcommand)) = (matches.is_present("WATCH"), matches.subcommand_name()) {
+        return Err(format_err!(
+            "--watch cannot be used with the `{}` subcommand",
+            subcommand
+        ));
+    }
+
     let mut builder = TodoRBuilder::new();
 
     // Search for global config file
@@ -73,6 +103,43 @@ fn run(matches: &ArgMatches) -> Result<i32, Error> {
         builder.set_no_style();
     }
 
+    if matches.is_present("IDS") || matches.subcommand_matches("show").is_some() {
+        builder.set_show_ids(true);
+    }
+
+    if let Some(column_names) = matches.values_of("COLUMNS") {
+        let columns = column_names
+            .map(Column::from_name)
+            .collect::<Result<Vec<Column>, Error>>()?;
+        builder.set_columns(columns);
+    }
+
+    if let Some(template) = matches.value_of("TEMPLATE") {
+        builder.set_template(template);
+    }
+
+    if matches.is_present("CACHE") {
+        builder.set_cache(true);
+    } else if matches.is_present("NO_CACHE") {
+        builder.set_cache(false);
+    }
+
+    if let Some(history_matches) = matches.subcommand_matches("history") {
+        let ignore_lines: Vec<&str> = match matches.values_of("IGNORE") {
+            Some(ignore_paths_iter) => ignore_paths_iter.collect(),
+            None => Vec::new(),
+        };
+        return run_history(history_matches, &builder, &ignore_lines);
+    }
+
+    let mut root_arg = matches.value_of("ROOT").map(Path::new);
+    if matches.is_present("RELATIVE_TO_ROOT") {
+        // search from the root so found paths are relative to it
+        let root = find_root(&builder, root_arg)?;
+        set_current_dir(&root)?;
+        root_arg = Some(Path::new("."));
+    }
+
     let curr_dir = current_dir()?;
     let mut ignore_builder = OverrideBuilder::new(&curr_dir);
     if let Some(ignore_paths_iter) = matches.values_of("IGNORE") {
@@ -88,7 +155,20 @@ fn run(matches: &ArgMatches) -> Result<i32, Error> {
         None
     };
 
+    let report_format = match matches.value_of("FORMAT") {
+        Some(format) => Some(parse_report_format(format)?),
+        None => None,
+    };
+
+    // 0 lets the walker pick the number of threads
+    let threads = match matches.value_of("THREADS") {
+        Some(threads) => threads.parse()?,
+        None => 0,
+    };
+
     let mut todor;
+    // walker and root of the workspace if it was searched
+    let mut workspace = None;
     if let Some(ext) = matches.value_of("EXT") {
         todor = builder.build()?;
         if atty::isnt(atty::Stream::Stdin) {
@@ -97,16 +177,67 @@ fn run(matches: &ArgMatches) -> Result<i32, Error> {
 
             todor.find_todos(&buffer, ext)?;
         }
+    } else if matches.is_present("REV") || matches.is_present("STAGED") {
+        if matches.subcommand_matches("rm").is_some() {
+            return Err(format_err!(
+                "`rm` edits the working tree, so it cannot be used with --rev or --staged"
+            ));
+        }
+
+        let tree = match matches.value_of("REV") {
+            Some(rev) => {
+                info!("reading files at `{}` from git...", rev);
+                GitTree::at_rev(rev)?
+            }
+            None => {
+                info!("reading staged files from git...");
+                GitTree::staged()?
+            }
+        };
+        tree.add_config_to(&mut builder);
+        let root = find_root(&builder, None).ok();
+        todor = builder.build()?;
+        debug!("todor parser built");
+        if let Some(root) = root {
+            todor.set_root(root);
+        }
+
+        let ignore_lines: Vec<&str> = match matches.values_of("IGNORE") {
+            Some(ignore_paths_iter) => ignore_paths_iter.collect(),
+            None => Vec::new(),
+        };
+        tree.for_each_searched_file(&ignore_lines, |path, content| {
+            todor.find_todos_at(content, tree.relative_path(path))
+        })?;
+
+        if let Some(p) = &pred {
+            todor.retain_todos(|_, todo| p(todo));
+        }
     } else {
         match matches.values_of("FILE") {
             Some(files) => {
+                add_todor_file_ignores(&mut ignore_builder)?;
                 let ignores = ignore_builder.build()?;
+                let root = find_root(&builder, None).ok();
                 todor = builder.build()?;
                 debug!("todor parser built");
+                if let Some(root) = root {
+                    todor.set_root(root);
+                }
                 for file in files {
-                    info!("looking at `{}`...", file);
+                    let path = Path::new(file);
+                    if path.is_dir() {
+                        info!("walking `{}`...", file);
+                        let walk = build_dir_walker(path, ignores.clone())
+                            .threads(threads)
+                            .build_parallel();
 
-                    if !ignores.matched(file, false).is_ignore() {
+                        // sort the files from each directory on their own to keep argument order
+                        let mut dir_todor = TodoR::with_parser(todor.parser().clone());
+                        open_todos_parallel(walk, &mut dir_todor, &pred);
+                        todor.merge(dir_todor);
+                    } else if !ignores.matched(file, false).is_ignore() {
+                        info!("looking at `{}`...", file);
                         todor
                             .open_option_filtered_todos(file, &pred)
                             .unwrap_or_else(|err| warn!("{}", err));
@@ -114,42 +245,122 @@ fn run(matches: &ArgMatches) -> Result<i32, Error> {
                 }
             }
             None => {
-                info!("Looking for .git or .todor to use as workspace root...");
-                let walk = build_walker(&mut builder, ignore_builder)?;
+                let (mut walk_builder, root) =
+                    build_walker(&mut builder, ignore_builder, root_arg)?;
+                if matches.is_present("TRACKED_ONLY") {
+                    let tracked = TrackedFiles::list(matches.is_present("RECURSE_SUBMODULES"))?;
+                    walk_builder.filter_entry(move |entry| match entry.file_type() {
+                        Some(file_type) if file_type.is_dir() => tracked.contains_dir(entry.path()),
+                        _ => tracked.contains_file(entry.path()),
+                    });
+                }
                 todor = builder.build()?;
                 debug!("todor parser built");
+                todor.set_root(&root);
 
-                for entry in walk {
-                    let dir_entry = entry?;
-                    let path = dir_entry.path().strip_prefix(".").unwrap();
+                if threads == 1 {
+                    for entry in walk_builder.build() {
+                        let dir_entry = entry?;
+                        let path = dir_entry.path().strip_prefix(".").unwrap();
 
-                    debug!("found {} in walk", path.display());
+                        debug!("found {} in walk", path.display());
 
-                    if path.is_file() {
-                        info!("looking at `{}`...", path.display());
-                        todor
-                            .open_todos(path)
-                            .unwrap_or_else(|err| warn!("{}", err));
+                        if path.is_file() {
+                            info!("looking at `{}`...", path.display());
+                            todor
+                                .open_option_filtered_todos(path, &pred)
+                                .unwrap_or_else(|err| warn!("{}", err));
+                        }
                     }
+                } else {
+                    debug!("walking with {} threads", threads);
+                    let walk = walk_builder.threads(threads).build_parallel();
+                    open_todos_parallel(walk, &mut todor, &pred);
                 }
+
+                todor
+                    .parser()
+                    .save_cache()
+                    .unwrap_or_else(|err| warn!("could not save cache: {}", err));
+
+                workspace = Some((walk_builder, root));
             }
         }
     }
 
+    if let Some(rev) = matches.value_of("SINCE") {
+        let changed_lines = ChangedLines::since(rev, matches.is_present("STAGED"))?;
+        todor.retain_todos(|filepath, todo| {
+            changed_lines.contains(filepath, todo.line, todo.end_line)
+        });
+    }
+
+    if let Some(baseline_matches) = matches.subcommand_matches("baseline") {
+        if baseline_matches.subcommand_matches("write").is_some() {
+            let root = workspace_root(&workspace)?;
+            let baseline_path = root.join(BASELINE_FILE_NAME);
+            let baseline = Baseline::from_todos(&todor, root);
+            baseline.save(&baseline_path)?;
+            info!(
+                "wrote {} TODOs to `{}`",
+                baseline.len(),
+                baseline_path.display()
+            );
+        }
+
+        return Ok(0);
+    }
+
+    if matches.is_present("BASELINE") {
+        let root = workspace_root(&workspace)?;
+        let baseline_path = root.join(BASELINE_FILE_NAME);
+        let baseline = Baseline::load(&baseline_path).map_err(|err| {
+            format_err!(
+                "could not read baseline `{}`: {}",
+                baseline_path.display(),
+                err
+            )
+        })?;
+
+        let stale = baseline.remove_known_todos(&mut todor, root);
+        if matches.is_present("WARN_STALE") {
+            for fingerprint in stale {
+                eprintln!("baseline TODO no longer found: {}", fingerprint);
+            }
+        }
+    }
+
+    let blame = matches.is_present("BLAME");
+    if blame {
+        todor.add_blame();
+    }
+
+    if let Some(show_matches) = matches.subcommand_matches("show") {
+        let id = todor
+            .find_todo_by_id(show_matches.value_of("ID").unwrap())?
+            .id();
+        let root = todor.root().map(Path::to_path_buf);
+        todor.retain_todos(|filepath, todo| todo_id(filepath, todo, root.as_deref()) == id);
+    }
+
+    if let Some(rm_matches) = matches.subcommand_matches("rm") {
+        todor.remove_todo_by_id(rm_matches.value_of("ID").unwrap())?;
+        println!("Comment removed");
+        return Ok(0);
+    }
+
+    if matches.is_present("WATCH") {
+        let (walk_builder, root) = workspace.as_ref().ok_or_else(|| {
+            format_err!("--watch can only be used when searching the whole workspace")
+        })?;
+        run_watch(walk_builder, root, &mut todor, &pred, &report_format, blame)?;
+        return Ok(0);
+    }
+
     if matches.is_present("DELETE_MODE") {
         run_delete(&mut todor)?;
-    } else if let Some(format) = matches.value_of("FORMAT") {
-        let report_format = match format {
-            "json" => ReportFormat::Json,
-            "prettyjson" => ReportFormat::JsonPretty,
-            "markdown" => ReportFormat::Markdown,
-            "usermarkdown" => ReportFormat::UserMarkdown,
-            "csv" => ReportFormat::Csv,
-            "default" => ReportFormat::Default,
-            _ => return Err(format_err!("invalid output format: {}.", format)),
-        };
-
-        todor.print_formatted_todos(&report_format)?;
+    } else if let Some(report_format) = &report_format {
+        todor.print_formatted_todos(report_format)?;
     } else {
         todor.print_todos();
     }
@@ -161,6 +372,86 @@ fn run(matches: &ArgMatches) -> Result<i32, Error> {
     Ok(0)
 }
 
+/// Gets the root of the workspace that was searched.
+fn workspace_root(workspace: &Option<(WalkBuilder, PathBuf)>) -> Result<&Path, Error> {
+    match workspace {
+        Some((_, root)) => Ok(root),
+        None => Err(format_err!(
+            "baselines can only be used when searching the whole workspace"
+        )),
+    }
+}
+
+fn parse_report_format(format: &str) -> Result<ReportFormat, Error> {
+    let report_format = match format {
+        "json" => ReportFormat::Json,
+        "prettyjson" => ReportFormat::JsonPretty,
+        "markdown" => ReportFormat::Markdown,
+        "usermarkdown" => ReportFormat::UserMarkdown,
+        "csv" => ReportFormat::Csv,
+        "tsv" => ReportFormat::Tsv,
+        "sarif" => ReportFormat::Sarif,
+        "github" => ReportFormat::GitHub,
+        "gitlab" => ReportFormat::GitLab,
+        "junit" => ReportFormat::JUnit,
+        "checkstyle" => ReportFormat::Checkstyle,
+        "template" => ReportFormat::Template,
+        "default" => ReportFormat::Default,
+        _ => return Err(format_err!("invalid output format: {}.", format)),
+    };
+
+    Ok(report_format)
+}
+
+/// Prints the number of TODOs in commits sampled from the git history.
+fn run_history(
+    matches: &ArgMatches,
+    builder: &TodoRBuilder,
+    ignore_lines: &[&str],
+) -> Result<i32, Error> {
+    let step = HistoryStep::from_name(matches.value_of("STEP").unwrap())?;
+    let commits = sample_commits(matches.value_of("SINCE"), step)?;
+
+    let history = commits
+        .iter()
+        .map(|commit| count_todos_at(commit, builder, ignore_lines))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // lock stdout to print faster
+    let stdout = io::stdout();
+    let mut out_buffer = io::BufWriter::new(stdout.lock());
+    match matches.value_of("FORMAT") {
+        Some("json") => write_history_json(&history, &mut out_buffer)?,
+        _ => write_history_csv(&history, &mut out_buffer)?,
+    }
+
+    Ok(0)
+}
+
+/// Prints the changes in TODOs between two saved reports.
+fn run_diff(matches: &ArgMatches) -> Result<i32, Error> {
+    let read_report_file = |path: &str| {
+        let file =
+            File::open(path).map_err(|err| format_err!("cannot open `{}`: {}", path, err))?;
+        read_report(BufReader::new(file))
+            .map_err(|err| format_err!("cannot read `{}`: {}", path, err))
+    };
+    let old = read_report_file(matches.value_of("OLD").unwrap())?;
+    let new = read_report_file(matches.value_of("NEW").unwrap())?;
+    let diff = TodoDiff::new(&old, &new);
+
+    // lock stdout to print faster
+    let stdout = io::stdout();
+    let mut out_buffer = io::BufWriter::new(stdout.lock());
+    match matches.value_of("FORMAT") {
+        Some("markdown") => diff.write_markdown(&mut out_buffer)?,
+        Some("json") => diff.write_json(&mut out_buffer)?,
+        _ => diff.write_text(&mut out_buffer)?,
+    }
+
+    Ok(0)
+}
+
 fn run_init() -> i32 {
     let mut config_file = match File::create(Path::new(".todor")) {
         Ok(file) => file,
@@ -16,13 +16,13 @@ pub fn run_delete(todor: &mut TodoR) -> Result<(), Error> {
         let filepath = Path::new(&file_selection);
         let selected_todo = select_todo(&todor, filepath)?;
 
-        let todo_ind = match selected_todo {
-            Some(todo_ind) => todo_ind,
+        let todo_id = match selected_todo {
+            Some(todo_id) => todo_id,
             None => continue,
         };
 
         todor
-            .remove_todo(filepath, todo_ind)
+            .remove_todo_by_id(&todo_id)
             .unwrap_or_else(|err| warn!("{}", err));
         println!("Comment removed");
     }
@@ -48,7 +48,8 @@ fn select_file(todor: &TodoR) -> Option<String> {
     Some(tracked_files[file_ind].to_string())
 }
 
-fn select_todo(todor: &TodoR, filepath: &Path) -> Result<Option<usize>, Error> {
+/// Lets the user pick a TODO in filepath and returns its ID.
+fn select_todo(todor: &TodoR, filepath: &Path) -> Result<Option<String>, Error> {
     let mut todos_buf: Vec<u8> = Vec::new();
     todor.write_todos_from_file(filepath, &mut todos_buf)?;
 
@@ -71,5 +72,10 @@ fn select_todo(todor: &TodoR, filepath: &Path) -> Result<Option<usize>, Error> {
         return Ok(None);
     }
 
-    Ok(Some(todo_ind))
+    let todo_id = todor
+        .iter()
+        .filter(|ptodo| ptodo.file() == filepath)
+        .nth(todo_ind)
+        .map(|ptodo| ptodo.id());
+    Ok(todo_id)
 }
@@ -1,68 +1,102 @@
 use config::FileFormat;
 use failure::{format_err, Error};
-use ignore::overrides::OverrideBuilder;
-use ignore::{Walk, WalkBuilder};
-use log::{debug, info};
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::{
+    DirEntry, ParallelVisitor, ParallelVisitorBuilder, WalkBuilder, WalkParallel, WalkState,
+};
+use log::{debug, info, warn};
+use std::cmp::Ordering;
 use std::env::current_dir;
-use std::path::{self, Path, PathBuf};
+use std::path::{self, Component, Path, PathBuf};
+use std::sync::mpsc::{channel, Sender};
 
-use todo_r::TodoRBuilder;
+use todo_r::todo::{Todo, TodoFile};
+use todo_r::{TodoParser, TodoR, TodoRBuilder};
 
-/// Recurses down and try to find either .git or .todor as the root folder.
+/// Name of the file in the workspace root that caches found TODOs.
+pub const CACHE_FILE_NAME: &str = ".todor-cache";
+/// Name of the file in the workspace root that holds the TODO baseline.
+pub const BASELINE_FILE_NAME: &str = ".todor-baseline";
+
+/// Recurses down and try to find either .todor or one of the root markers, like .git or .hg,
+/// to use as the root folder. If root is given, it is used as the root folder instead.
+///
+/// Returns the absolute path to the root folder.
+pub fn find_root(todor_builder: &TodoRBuilder, root: Option<&Path>) -> Result<PathBuf, Error> {
+    let curr_dir = current_dir()?.canonicalize()?;
+    if let Some(root) = root {
+        return curr_dir
+            .join(root)
+            .canonicalize()
+            .map_err(|err| format_err!("cannot use `{}` as root: {}", root.display(), err));
+    }
+
+    let markers = todor_builder.root_markers()?;
+    let markers: Vec<&str> = std::iter::once(".todor")
+        .chain(markers.iter().map(String::as_str))
+        .collect();
+    info!(
+        "Looking for {} to use as workspace root...",
+        markers.join(", ")
+    );
+
+    for dir in curr_dir.ancestors() {
+        if let Some(marker) = markers.iter().map(|m| dir.join(m)).find(|m| m.exists()) {
+            info!("Found workspace root: '{}'", marker.display());
+            return Ok(dir.to_path_buf());
+        }
+    }
+
+    Err(format_err!(
+        "No input files provided and no workspace root found. Looked for {}",
+        markers.join(", ")
+    ))
+}
+
+/// Finds the workspace root using `find_root()` and applies the .todor config in it.
 /// Ignore builder should be initialized relative to current_dir().
 ///
-/// Returns an iterator that iterates over all the tracked files.
+/// Returns a builder for walkers that iterate over all the tracked files
+/// along with the relative path to the workspace root.
 /// Measures are taken to make sure paths are returned in a nice relative path format.
+///
+/// The TODO cache file is set to `.todor-cache` in the workspace root.
 pub fn build_walker(
     todor_builder: &mut TodoRBuilder,
     mut ignore_builder: OverrideBuilder,
-) -> Result<Walk, Error> {
-    info!("Looking for .git or .todor to use as workspace root...");
+    root: Option<&Path>,
+) -> Result<(WalkBuilder, PathBuf), Error> {
+    let root_dir = find_root(todor_builder, root)?;
+    apply_todor_config(todor_builder, &root_dir.join(".todor"))?;
 
-    let mut curr_dir = current_dir()?;
-    // let mut ignore_builder = OverrideBuilder::new(&curr_dir);
-    curr_dir.push(".todor");
+    let mut curr_dir = current_dir()?.canonicalize()?;
     let mut relative_path = PathBuf::from(".");
-    let mut walk_builder = WalkBuilder::new(&relative_path);
-    let mut found_walker_root = false;
-
-    for abs_path in curr_dir.ancestors() {
-        // ignore previous directory to not get repeated equivalent paths
-        let ignore_string = get_ignore_string(abs_path, &relative_path)?;
-        debug!("adding {} in walker override", &ignore_string);
-        ignore_builder.add(&ignore_string).unwrap();
-
-        // check for .todor
-        let todor_path = abs_path.with_file_name(".todor");
-        if todor_path.exists() {
-            found_walker_root = true;
-            info!("Found workspace root: '{}'", todor_path.display());
-            info!("Applying config file '{}'...", todor_path.display());
-
-            // check for empty file before adding
-            if todor_path.metadata().unwrap().len() > 2 {
-                todor_builder.add_config_file_with_format(todor_path, FileFormat::Hjson)?;
+    let mut walk_builder;
+    if curr_dir.starts_with(&root_dir) {
+        // walk each directory up to the root so paths stay relative to the current directory
+        curr_dir.push(".todor");
+        walk_builder = WalkBuilder::new(&relative_path);
+        for abs_path in curr_dir.ancestors() {
+            // ignore previous directory to not get repeated equivalent paths
+            let ignore_string = get_ignore_string(abs_path, &relative_path)?;
+            debug!("adding {} in walker override", &ignore_string);
+            ignore_builder.add(&ignore_string).unwrap();
+
+            if abs_path.parent() == Some(root_dir.as_path()) {
+                break;
             }
-            break;
-        }
 
-        // check for .git
-        let git_path = abs_path.with_file_name(".git");
-        if git_path.exists() {
-            found_walker_root = true;
-            info!("Found workspace root: '{}'", git_path.display());
-            break;
+            relative_path.push("..");
+            walk_builder.add(&relative_path);
         }
-
-        relative_path.push("..");
-        walk_builder.add(&relative_path);
+    } else {
+        // roots that are not above the current directory are walked on their own
+        relative_path.push(relative_dir(&curr_dir, &root_dir));
+        walk_builder = WalkBuilder::new(&relative_path);
     }
 
-    if !found_walker_root {
-        return Err(format_err!(
-            "No input files provided and no git repo or todor workspace found"
-        ));
-    }
+    todor_builder.set_cache_file(relative_path.join(CACHE_FILE_NAME));
+    add_todor_file_ignores(&mut ignore_builder)?;
 
     walk_builder
         .overrides(ignore_builder.build()?)
@@ -70,19 +104,87 @@ pub fn build_walker(
         .add_custom_ignore_filename(".todorignore")
         .parents(false);
 
-    Ok(walk_builder.build())
+    Ok((walk_builder, relative_path))
+}
+
+/// Builds a walker over the directory at dirpath that skips the same files as the workspace
+/// walker. Ignore files in the parents of dirpath are also used.
+pub fn build_dir_walker(dirpath: &Path, ignores: Override) -> WalkBuilder {
+    let mut walk_builder = WalkBuilder::new(dirpath);
+    walk_builder
+        .overrides(ignores)
+        .sort_by_file_name(std::ffi::OsStr::cmp)
+        .add_custom_ignore_filename(".todorignore");
+
+    walk_builder
+}
+
+/// Adds overrides to skip the files todor writes in the workspace root.
+pub fn add_todor_file_ignores(ignore_builder: &mut OverrideBuilder) -> Result<(), Error> {
+    ignore_builder.add(&format!("!{}", CACHE_FILE_NAME))?;
+    ignore_builder.add(&format!("!{}", BASELINE_FILE_NAME))?;
+    Ok(())
+}
+
+/// Adds the config in the .todor file at todor_path if there is one.
+fn apply_todor_config(todor_builder: &mut TodoRBuilder, todor_path: &Path) -> Result<(), Error> {
+    // check for empty file before adding
+    if todor_path.exists() && todor_path.metadata()?.len() > 2 {
+        info!("Applying config file '{}'...", todor_path.display());
+        todor_builder.add_config_file_with_format(todor_path, FileFormat::Hjson)?;
+    }
+
+    Ok(())
+}
+
+/// Gets the path to the directory to from the directory from. Both paths must be absolute.
+fn relative_dir(from: &Path, to: &Path) -> PathBuf {
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..from.components().count() {
+        relative.push("..");
+    }
+    for part in to.components().skip(common) {
+        relative.push(part);
+    }
+    relative
+}
+
+/// Finds TODOs in every file from walk using the parser of todor from each thread.
+/// The TODOs found are added to todor and sorted in the same order
+/// a sequential walk would have found them in.
+pub fn open_todos_parallel<P>(walk: WalkParallel, todor: &mut TodoR, pred: &Option<P>)
+where
+    P: Fn(&Todo) -> bool + Sync,
+{
+    let (tx, rx) = channel();
+    let mut visitor_builder = TodoVisitorBuilder {
+        parser: todor.parser(),
+        pred,
+        tx,
+    };
+    walk.visit(&mut visitor_builder);
+    drop(visitor_builder);
+
+    for todo_file in rx {
+        todor.add_todo_file(todo_file);
+    }
+    todor.sort_files_by(cmp_walk_paths);
 }
 
 /// Gets the ignore string for ignore::overrides::OverrideBuilder to use.
 /// Uses the fact that the file_name in abs_path is the previous directory.
 fn get_ignore_string(abs_path: &Path, rel_path: &Path) -> Result<String, Error> {
-    let ignore_path =
-        rel_path
-            .strip_prefix(".")
-            .unwrap()
-            .with_file_name(abs_path.file_name().ok_or_else(|| {
-                format_err!("No input files provided and no git repo or todor workspace found")
-            })?);
+    let ignore_path = rel_path.strip_prefix(".").unwrap().with_file_name(
+        abs_path
+            .file_name()
+            .ok_or_else(|| format_err!("No input files provided and no workspace root found"))?,
+    );
 
     let ignore_path_str = ignore_path.to_str().ok_or_else(|| {
         format_err!(
@@ -102,3 +204,75 @@ fn get_ignore_string(abs_path: &Path, rel_path: &Path) -> Result<String, Error>
 
     Ok(ignore_string)
 }
+
+/// Compares paths in the order that the walker from `build_walker()` visits them.
+/// Paths are grouped by the walker root they came from before being compared by file name.
+pub fn cmp_walk_paths(a: &Path, b: &Path) -> Ordering {
+    num_parent_dirs(a)
+        .cmp(&num_parent_dirs(b))
+        .then_with(|| a.cmp(b))
+}
+
+/// Counts the leading `..` components in path. This is the index of its walker root.
+fn num_parent_dirs(path: &Path) -> usize {
+    path.components()
+        .take_while(|c| *c == Component::ParentDir)
+        .count()
+}
+
+/// Builds a TodoVisitor for each thread in a parallel walk.
+struct TodoVisitorBuilder<'s, P> {
+    parser: &'s TodoParser,
+    pred: &'s Option<P>,
+    tx: Sender<TodoFile>,
+}
+
+impl<'s, P> ParallelVisitorBuilder<'s> for TodoVisitorBuilder<'s, P>
+where
+    P: Fn(&Todo) -> bool + Sync,
+{
+    fn build(&mut self) -> Box<dyn ParallelVisitor + 's> {
+        Box::new(TodoVisitor {
+            parser: self.parser,
+            pred: self.pred,
+            tx: self.tx.clone(),
+        })
+    }
+}
+
+/// Finds TODOs in the files visited by one thread and sends them back to be collected.
+struct TodoVisitor<'s, P> {
+    parser: &'s TodoParser,
+    pred: &'s Option<P>,
+    tx: Sender<TodoFile>,
+}
+
+impl<'s, P> ParallelVisitor for TodoVisitor<'s, P>
+where
+    P: Fn(&Todo) -> bool + Sync,
+{
+    fn visit(&mut self, entry: Result<DirEntry, ignore::Error>) -> WalkState {
+        let dir_entry = match entry {
+            Ok(dir_entry) => dir_entry,
+            Err(err) => {
+                warn!("{}", err);
+                return WalkState::Continue;
+            }
+        };
+        let path = dir_entry.path();
+        let path = path.strip_prefix(".").unwrap_or(path);
+
+        debug!("found {} in walk", path.display());
+
+        if path.is_file() {
+            info!("looking at `{}`...", path.display());
+            match self.parser.parse_option_filtered_file(path, self.pred) {
+                // receiver outlives the walk so this cannot fail
+                Ok(todo_file) => self.tx.send(todo_file).unwrap(),
+                Err(err) => warn!("{}", err),
+            }
+        }
+
+        WalkState::Continue
+    }
+}
@@ -0,0 +1,280 @@
+// Module for watching the workspace and reporting TODOs again when files change
+
+use failure::Error;
+use fnv::FnvHashSet;
+use ignore::WalkBuilder;
+use log::{debug, info, warn};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::env::current_dir;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+use todo_r::format::ReportFormat;
+use todo_r::todo::{PathedTodo, Todo, TodoFile};
+use todo_r::TodoR;
+
+use crate::walk::{cmp_walk_paths, CACHE_FILE_NAME};
+
+/// Time to wait for more file events before handling the ones received.
+/// Editors and git often write several files at once.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Watches the workspace at root and updates the TODOs in todor as files change.
+/// TODOs are printed once at the start and again after every change.
+///
+/// JSON formats are written as newline delimited JSON `add` and `remove` events instead.
+/// If blame is true, git blame information is added to TODOs in files that change.
+/// This function only returns if watching fails.
+pub fn run_watch<P>(
+    walk_builder: &WalkBuilder,
+    root: &Path,
+    todor: &mut TodoR,
+    pred: &Option<P>,
+    report_format: &Option<ReportFormat>,
+    blame: bool,
+) -> Result<(), Error>
+where
+    P: Fn(&Todo) -> bool,
+{
+    let (tx, rx) = channel();
+    let mut watcher = RecommendedWatcher::new(tx, notify::Config::default())?;
+    let root = root.canonicalize()?;
+    watcher.watch(&root, RecursiveMode::Recursive)?;
+    info!("watching `{}` for changes...", root.display());
+
+    let curr_dir = current_dir()?.canonicalize()?;
+    let mut tracked: FnvHashSet<PathBuf> = todor
+        .get_all_tracked_files()
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+
+    let emit_events = matches!(
+        report_format,
+        Some(ReportFormat::Json) | Some(ReportFormat::JsonPretty)
+    );
+    if emit_events {
+        for todo_file in todor.iter_files() {
+            write_events(&TodoFile::new(&todo_file.filepath), todo_file, todor.root())?;
+        }
+    } else {
+        redraw(todor, report_format)?;
+    }
+
+    while let Some(changed) = recv_changes(&rx, &curr_dir) {
+        debug!("files changed: {:?}", changed);
+
+        // Changes to tracked files can be parsed right away. Anything else might change which
+        // files are tracked, so the walk is run again to apply the ignore rules.
+        let needs_walk = changed
+            .iter()
+            .any(|path| !tracked.contains(path) || !path.is_file());
+
+        let mut to_parse: Vec<PathBuf> = Vec::new();
+        let mut removed: Vec<PathBuf> = Vec::new();
+        if needs_walk {
+            let walk_files = walk_files(walk_builder);
+            removed.extend(tracked.difference(&walk_files).cloned());
+            to_parse.extend(
+                walk_files
+                    .iter()
+                    .filter(|path| changed.contains(*path) || !tracked.contains(*path))
+                    .cloned(),
+            );
+            tracked = walk_files;
+        } else {
+            to_parse.extend(changed);
+        }
+
+        let mut updated = false;
+        for path in removed {
+            if let Some(old_file) = todor.remove_todo_file(&path) {
+                updated |= !old_file.is_empty();
+                if emit_events {
+                    write_events(&old_file, &TodoFile::new(&path), todor.root())?;
+                }
+            }
+        }
+
+        for path in to_parse {
+            info!("looking at `{}`...", path.display());
+            let mut new_file = match todor.parser().parse_option_filtered_file(&path, pred) {
+                Ok(new_file) => new_file,
+                Err(err) => {
+                    warn!("{}", err);
+                    continue;
+                }
+            };
+            if blame {
+                new_file
+                    .add_blame()
+                    .unwrap_or_else(|err| warn!("cannot blame `{}`: {}", path.display(), err));
+            }
+
+            let old_file = todor
+                .replace_todo_file(new_file.clone())
+                .unwrap_or_else(|| TodoFile::new(&path));
+            updated |= old_file.todos != new_file.todos;
+            if emit_events {
+                write_events(&old_file, &new_file, todor.root())?;
+            }
+        }
+
+        todor.sort_files_by(cmp_walk_paths);
+        todor
+            .parser()
+            .save_cache()
+            .unwrap_or_else(|err| warn!("could not save cache: {}", err));
+
+        if updated && !emit_events {
+            redraw(todor, report_format)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Waits for files to change and returns their paths in the format the workspace walker uses.
+/// Returns None once the watcher stops.
+fn recv_changes(
+    rx: &Receiver<notify::Result<Event>>,
+    curr_dir: &Path,
+) -> Option<FnvHashSet<PathBuf>> {
+    let mut changed = FnvHashSet::default();
+    while changed.is_empty() {
+        add_changed_paths(&mut changed, rx.recv().ok()?, curr_dir);
+
+        // collect events that come right after so they are handled together
+        while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+            add_changed_paths(&mut changed, event, curr_dir);
+        }
+    }
+
+    Some(changed)
+}
+
+fn add_changed_paths(
+    changed: &mut FnvHashSet<PathBuf>,
+    event: notify::Result<Event>,
+    curr_dir: &Path,
+) {
+    let event = match event {
+        Ok(event) => event,
+        Err(err) => {
+            warn!("{}", err);
+            return;
+        }
+    };
+
+    if let EventKind::Access(_) = event.kind {
+        return;
+    }
+
+    for path in event.paths {
+        if is_ignored_change(&path) {
+            continue;
+        }
+
+        if let Some(walk_path) = walk_relative_path(&path, curr_dir) {
+            changed.insert(walk_path);
+        }
+    }
+}
+
+/// Returns true for changes that never affect TODOs like git internals and the TODO cache.
+fn is_ignored_change(path: &Path) -> bool {
+    let in_git_dir = path
+        .components()
+        .any(|c| c == Component::Normal(".git".as_ref()));
+    let is_cache_file = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with(CACHE_FILE_NAME));
+
+    in_git_dir || is_cache_file
+}
+
+/// Converts an absolute path into the relative path format the workspace walker gives.
+/// Files outside of the current directory are given relative to it using `..` components.
+fn walk_relative_path(abs_path: &Path, curr_dir: &Path) -> Option<PathBuf> {
+    curr_dir
+        .ancestors()
+        .enumerate()
+        .find_map(|(num_parents, ancestor)| {
+            let rel_path = abs_path.strip_prefix(ancestor).ok()?;
+            let mut walk_path = PathBuf::new();
+            for _ in 0..num_parents {
+                walk_path.push("..");
+            }
+            walk_path.push(rel_path);
+            Some(walk_path)
+        })
+}
+
+/// Walks the workspace again to get every file that should be searched.
+fn walk_files(walk_builder: &WalkBuilder) -> FnvHashSet<PathBuf> {
+    walk_builder
+        .build()
+        .filter_map(|entry| entry.map_err(|err| warn!("{}", err)).ok())
+        .map(|dir_entry| dir_entry.path().strip_prefix(".").unwrap().to_owned())
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Clears the terminal and prints all the TODOs again.
+fn redraw(todor: &TodoR, report_format: &Option<ReportFormat>) -> Result<(), Error> {
+    if atty::is(atty::Stream::Stdout) {
+        // clear screen and move cursor to the top left
+        print!("\x1B[2J\x1B[H");
+    }
+
+    match report_format {
+        Some(report_format) => todor.print_formatted_todos(report_format)?,
+        None => todor.print_todos(),
+    }
+
+    io::stdout().flush()?;
+    Ok(())
+}
+
+/// A TODO that was added or removed while watching.
+#[derive(Serialize)]
+struct WatchEvent<'a> {
+    event: &'static str,
+    #[serde(flatten)]
+    todo: PathedTodo<'a>,
+}
+
+/// Writes an event for every TODO that is only in one of old_file and new_file.
+/// IDs are made relative to root.
+fn write_events(
+    old_file: &TodoFile,
+    new_file: &TodoFile,
+    root: Option<&Path>,
+) -> Result<(), Error> {
+    let stdout = io::stdout();
+    let mut out_buffer = io::BufWriter::new(stdout.lock());
+
+    let removed = old_file
+        .iter_in_root(root)
+        .filter(|ptodo| !new_file.todos.contains(ptodo.todo()))
+        .map(|todo| WatchEvent {
+            event: "remove",
+            todo,
+        });
+    let added = new_file
+        .iter_in_root(root)
+        .filter(|ptodo| !old_file.todos.contains(ptodo.todo()))
+        .map(|todo| WatchEvent { event: "add", todo });
+
+    for event in removed.chain(added) {
+        serde_json::to_writer(&mut out_buffer, &event)?;
+        writeln!(out_buffer)?;
+    }
+
+    out_buffer.flush()?;
+    Ok(())
+}
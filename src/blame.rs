@@ -0,0 +1,223 @@
+// Module for finding who last changed TODO lines using git blame
+
+use failure::Error;
+use fnv::FnvHashMap;
+use std::ffi::OsString;
+
+use crate::git::run_git;
+use crate::todo::{Blame, TodoFile};
+
+/// Runs git blame on the lines of every TODO in todo_file and attaches the results to them.
+pub(crate) fn blame_todo_file(todo_file: &mut TodoFile) -> Result<(), Error> {
+    if todo_file.is_empty() {
+        return Ok(());
+    }
+
+    let filepath = &todo_file.filepath;
+    let mut args: Vec<OsString> = vec!["blame".into(), "--porcelain".into()];
+    for todo in &todo_file.todos {
+        args.push(format!("-L{},{}", todo.line, todo.line).into());
+    }
+    args.push("--".into());
+    args.push(filepath.file_name().unwrap_or(filepath.as_os_str()).into());
+
+    // run from the file's directory so the repository containing the file is used
+    let dir = filepath.parent().filter(|dir| !dir.as_os_str().is_empty());
+    let output = run_git(dir, &args)?;
+
+    let mut blames = parse_porcelain(&String::from_utf8_lossy(&output));
+    for todo in &mut todo_file.todos {
+        todo.blame = blames.remove(&todo.line);
+    }
+
+    Ok(())
+}
+
+/// Parses the output of `git blame --porcelain` into the Blame for each line.
+///
+/// Commit information is only given the first time a commit shows up in the output
+/// so it is saved for later lines from the same commit.
+fn parse_porcelain(porcelain: &str) -> FnvHashMap<usize, Blame> {
+    let mut commits: FnvHashMap<&str, CommitInfo> = FnvHashMap::default();
+    let mut line_commits: Vec<(usize, &str)> = Vec::new();
+    let mut current_commit = "";
+
+    for line in porcelain.lines() {
+        if line.starts_with('\t') {
+            // content of the blamed line ends its entry
+            continue;
+        }
+
+        let (key, value) = match line.find(' ') {
+            Some(i) => (&line[..i], &line[i + 1..]),
+            None => (line, ""),
+        };
+
+        if is_commit_hash(key) {
+            // header is `<commit> <original line> <final line> [<number of lines>]`
+            current_commit = key;
+            if let Some(final_line) = value.split(' ').nth(1).and_then(|l| l.parse().ok()) {
+                line_commits.push((final_line, key));
+            }
+            commits.entry(key).or_default();
+            continue;
+        }
+
+        let info = commits.entry(current_commit).or_default();
+        match key {
+            "author" => info.author = value,
+            "author-mail" => {
+                info.author_email = value.trim_start_matches('<').trim_end_matches('>')
+            }
+            "author-time" => info.time = value.parse().unwrap_or(0),
+            "author-tz" => info.tz = value,
+            _ => {}
+        }
+    }
+
+    line_commits
+        .into_iter()
+        .map(|(line, commit)| {
+            let info = &commits[commit];
+            let blame = Blame {
+                author: info.author.to_string(),
+                author_email: info.author_email.to_string(),
+                commit: commit.to_string(),
+                date: format_date(info.time, info.tz),
+            };
+            (line, blame)
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct CommitInfo<'a> {
+    author: &'a str,
+    author_email: &'a str,
+    time: i64,
+    tz: &'a str,
+}
+
+fn is_commit_hash(s: &str) -> bool {
+    s.len() == 40 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Formats seconds since the unix epoch as an ISO 8601 date in the timezone tz given as `+hhmm`.
+fn format_date(time: i64, tz: &str) -> String {
+    let (tz_offset, tz_str) = match parse_tz_offset(tz) {
+        Some(offset) => (offset, format!("{}:{}", &tz[..3], &tz[3..])),
+        None => (0, "Z".to_string()),
+    };
+    let local_time = time + tz_offset;
+    let days = local_time.div_euclid(86400);
+    let secs = local_time.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+        tz_str
+    )
+}
+
+/// Parses timezones like `+0130` into an offset in seconds.
+fn parse_tz_offset(tz: &str) -> Option<i64> {
+    if tz.len() != 5 || !tz.is_char_boundary(1) {
+        return None;
+    }
+
+    let sign = match &tz[..1] {
+        "+" => 1,
+        "-" => -1,
+        _ => return None,
+    };
+    let hours: i64 = tz[1..3].parse().ok()?;
+    let minutes: i64 = tz[3..].parse().ok()?;
+
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Converts days since the unix epoch to a (year, month, day) date.
+/// Uses the algorithm from http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PORCELAIN: &str = "\
+1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b 3 2 1
+author Lavi Blumberg
+author-mail <lavifb@gmail.com>
+author-time 1547164800
+author-tz -0500
+committer Lavi Blumberg
+committer-mail <lavifb@gmail.com>
+committer-time 1547164800
+committer-tz -0500
+summary Add things
+filename test.rs
+\t// TODO: item
+0000000000000000000000000000000000000000 5 5 1
+author Not Committed Yet
+author-mail <not.committed.yet>
+author-time 1579219200
+author-tz +0000
+committer Not Committed Yet
+committer-mail <not.committed.yet>
+committer-time 1579219200
+committer-tz +0000
+summary Version of test.rs from test.rs
+previous 1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b test.rs
+filename test.rs
+\t// TODO: new item
+1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b 6 8 1
+\t// TODO: later item
+";
+
+    #[test]
+    fn porcelain() {
+        let blames = parse_porcelain(PORCELAIN);
+        assert_eq!(blames.len(), 3);
+
+        let blame = &blames[&2];
+        assert_eq!(blame.author, "Lavi Blumberg");
+        assert_eq!(blame.author_email, "lavifb@gmail.com");
+        assert_eq!(blame.commit, "1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b");
+        assert_eq!(blame.date, "2019-01-10T19:00:00-05:00");
+
+        assert_eq!(blames[&5].author, "Not Committed Yet");
+        assert_eq!(blames[&5].date, "2020-01-17T00:00:00+00:00");
+
+        // repeated commits reuse the info given the first time
+        assert_eq!(blames[&8].author, "Lavi Blumberg");
+        assert_eq!(blames[&8].date, blame.date);
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(format_date(0, "+0000"), "1970-01-01T00:00:00+00:00");
+        assert_eq!(
+            format_date(951_782_400, "+0130"),
+            "2000-02-29T01:30:00+01:30"
+        );
+        assert_eq!(format_date(1_547_164_800, "bad"), "2019-01-11T00:00:00Z");
+    }
+}
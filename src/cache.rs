@@ -0,0 +1,367 @@
+// Module for caching found TODOs between runs
+
+use failure::Error;
+use fnv::{FnvHashMap, FnvHasher};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::env::current_dir;
+use std::fs::{self, File, Metadata};
+use std::hash::Hasher;
+use std::io::{BufReader, BufWriter};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::todo::{count_occurrences, Todo};
+
+/// Cache of the TODOs found in each file along with the file metadata when they were found.
+///
+/// Lookups are made against the cache loaded from disk.
+/// Every file looked up or parsed is recorded in a new cache that is what gets saved,
+/// so files that no longer exist are dropped from the cache file.
+#[derive(Debug)]
+pub(crate) struct TodoCache {
+    cache_path: PathBuf,
+    base_dir: PathBuf,
+    config_hash: u64,
+    saved_secs: u64,
+    old_files: FnvHashMap<PathBuf, CachedFile>,
+    new_files: Mutex<FnvHashMap<PathBuf, CachedFile>>,
+}
+
+impl TodoCache {
+    /// Loads the cache at cache_path.
+    /// The cache starts empty if the file is missing, cannot be read,
+    /// or was written with a different config.
+    pub fn load(cache_path: PathBuf, config_hash: u64) -> TodoCache {
+        let (saved_secs, old_files) = match read_cache_file(&cache_path) {
+            Ok(cache_file) if cache_file.config_hash == config_hash => (
+                cache_file.saved_secs,
+                cache_file
+                    .files
+                    .into_iter()
+                    .map(|f| (f.path.clone(), f))
+                    .collect(),
+            ),
+            Ok(_) => {
+                debug!("config changed since cache was written. Ignoring cache.");
+                (0, FnvHashMap::default())
+            }
+            Err(err) => {
+                debug!("could not read cache `{}`: {}", cache_path.display(), err);
+                (0, FnvHashMap::default())
+            }
+        };
+
+        TodoCache {
+            cache_path,
+            base_dir: current_dir().unwrap_or_default(),
+            config_hash,
+            saved_secs,
+            old_files,
+            new_files: Mutex::new(FnvHashMap::default()),
+        }
+    }
+
+    /// Returns the key used to cache filepath.
+    /// Keys are absolute so the same file is found when running from a different directory.
+    pub fn key(&self, filepath: &Path) -> PathBuf {
+        normalize_path(&self.base_dir.join(filepath))
+    }
+
+    /// Returns the cached TODOs for key if the file size and modified time match stamp.
+    ///
+    /// Files modified in the same second the cache was saved could have changed again without
+    /// their modified time changing, so they are never treated as unchanged.
+    pub fn get_unchanged(&self, key: &Path, stamp: &FileStamp) -> Option<Vec<Todo>> {
+        let cached = self.old_files.get(key)?;
+        if cached.stamp != *stamp || stamp.modified_secs >= self.saved_secs {
+            return None;
+        }
+
+        self.record(cached.clone());
+        Some(cached.todos())
+    }
+
+    /// Returns the cached TODOs for key if the file content still has the same hash.
+    pub fn get_same_content(&self, key: &Path, content_hash: u64) -> Option<Vec<Todo>> {
+        let cached = self.old_files.get(key)?;
+        if cached.content_hash != content_hash {
+            return None;
+        }
+
+        Some(cached.todos())
+    }
+
+    /// Records the TODOs found in the file for key so they are saved in the cache.
+    pub fn insert(&self, key: PathBuf, stamp: FileStamp, content_hash: u64, todos: &[Todo]) {
+        self.record(CachedFile {
+            path: key,
+            stamp,
+            content_hash,
+            todos: todos.iter().map(CachedTodo::from).collect(),
+        });
+    }
+
+    fn record(&self, cached: CachedFile) {
+        self.new_files
+            .lock()
+            .unwrap()
+            .insert(cached.path.clone(), cached);
+    }
+
+    /// Writes every file recorded in this run to the cache file.
+    pub fn save(&self) -> Result<(), Error> {
+        let new_files = self.new_files.lock().unwrap();
+        let mut files: Vec<&CachedFile> = new_files.values().collect();
+        // sort so the cache file does not change when nothing else does
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let saved_secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let cache_file = CacheFileSerial {
+            config_hash: self.config_hash,
+            saved_secs,
+            files,
+        };
+
+        // write to a temporary file first so an interrupted run cannot leave a broken cache
+        let tmp_path = self.cache_path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        serde_json::to_writer(&mut writer, &cache_file)?;
+        drop(writer);
+        fs::rename(&tmp_path, &self.cache_path)?;
+
+        debug!(
+            "saved {} files to cache `{}`",
+            new_files.len(),
+            self.cache_path.display()
+        );
+        Ok(())
+    }
+}
+
+/// Size and modified time of a file used to tell if it changed without reading it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct FileStamp {
+    size: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+}
+
+impl FileStamp {
+    pub fn new(metadata: &Metadata) -> FileStamp {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+
+        FileStamp {
+            size: metadata.len(),
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+        }
+    }
+}
+
+/// Hashes file content to tell if a file changed when its metadata did.
+pub(crate) fn hash_content(content: &[u8]) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(content);
+    hasher.finish()
+}
+
+/// Layout of the cache file on disk.
+#[derive(Serialize)]
+struct CacheFileSerial<'a> {
+    config_hash: u64,
+    saved_secs: u64,
+    files: Vec<&'a CachedFile>,
+}
+
+/// Owned version of `CacheFileSerial` for reading the cache file.
+#[derive(Deserialize)]
+struct CacheFileDeserial {
+    config_hash: u64,
+    saved_secs: u64,
+    files: Vec<CachedFile>,
+}
+
+fn read_cache_file(cache_path: &Path) -> Result<CacheFileDeserial, Error> {
+    let reader = BufReader::new(File::open(cache_path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedFile {
+    path: PathBuf,
+    #[serde(flatten)]
+    stamp: FileStamp,
+    content_hash: u64,
+    todos: Vec<CachedTodo>,
+}
+
+impl CachedFile {
+    fn todos(&self) -> Vec<Todo> {
+        let mut todos: Vec<Todo> = self.todos.iter().map(Todo::from).collect();
+        count_occurrences(&mut todos);
+        todos
+    }
+}
+
+/// Todo as it is stored in the cache.
+/// This is kept separate from the output format of `Todo` so either can change on its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedTodo {
+    line: usize,
+    end_line: usize,
+    tag: String,
+    content: String,
+}
+
+impl From<&Todo> for CachedTodo {
+    fn from(todo: &Todo) -> CachedTodo {
+        CachedTodo {
+            line: todo.line,
+            end_line: todo.end_line,
+            tag: todo.tag.clone(),
+            content: todo.content.clone(),
+        }
+    }
+}
+
+impl From<&CachedTodo> for Todo {
+    fn from(cached: &CachedTodo) -> Todo {
+        Todo {
+            line: cached.line,
+            end_line: cached.end_line,
+            tag: cached.tag.clone(),
+            content: cached.content.clone(),
+            occurrence: 0,
+            blame: None,
+        }
+    }
+}
+
+/// Removes `.` and `..` components from path without touching the file system.
+pub(crate) fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_stamp(size: u64) -> FileStamp {
+        FileStamp {
+            size,
+            modified_secs: 100,
+            modified_nanos: 0,
+        }
+    }
+
+    fn test_cache_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("todor-cache-test-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn normalize() {
+        assert_eq!(
+            normalize_path(Path::new("/a/b/./../c/d.rs")),
+            PathBuf::from("/a/c/d.rs")
+        );
+    }
+
+    #[test]
+    fn save_and_load() {
+        let cache_path = test_cache_path("save");
+        let cache = TodoCache::load(cache_path.clone(), 1);
+        let key = cache.key(Path::new("src/main.rs"));
+        let todos = vec![Todo::new(2, "todo", "item")];
+        cache.insert(key.clone(), test_stamp(10), 42, &todos);
+        cache.save().unwrap();
+
+        let cache = TodoCache::load(cache_path.clone(), 1);
+        let cached = cache.get_unchanged(&key, &test_stamp(10)).unwrap();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0].line, 2);
+        assert_eq!(cached[0].tag, "TODO");
+        assert_eq!(cached[0].content, "item");
+
+        assert!(cache.get_unchanged(&key, &test_stamp(11)).is_none());
+        assert!(cache.get_same_content(&key, 42).is_some());
+        assert!(cache.get_same_content(&key, 43).is_none());
+
+        fs::remove_file(cache_path).unwrap();
+    }
+
+    #[test]
+    fn recently_modified_not_trusted() {
+        let cache_path = test_cache_path("recent");
+        let cache = TodoCache::load(cache_path.clone(), 1);
+        let key = cache.key(Path::new("main.rs"));
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let stamp = FileStamp {
+            size: 10,
+            modified_secs: now.as_secs(),
+            modified_nanos: 0,
+        };
+        cache.insert(key.clone(), stamp.clone(), 42, &[]);
+        cache.save().unwrap();
+
+        let cache = TodoCache::load(cache_path.clone(), 1);
+        assert!(cache.get_unchanged(&key, &stamp).is_none());
+        assert!(cache.get_same_content(&key, 42).is_some());
+
+        fs::remove_file(cache_path).unwrap();
+    }
+
+    #[test]
+    fn config_change_invalidates() {
+        let cache_path = test_cache_path("config");
+        let cache = TodoCache::load(cache_path.clone(), 1);
+        let key = cache.key(Path::new("main.rs"));
+        cache.insert(key.clone(), test_stamp(10), 42, &[]);
+        cache.save().unwrap();
+
+        let cache = TodoCache::load(cache_path.clone(), 2);
+        assert!(cache.get_unchanged(&key, &test_stamp(10)).is_none());
+
+        fs::remove_file(cache_path).unwrap();
+    }
+
+    #[test]
+    fn unseen_files_dropped() {
+        let cache_path = test_cache_path("unseen");
+        let cache = TodoCache::load(cache_path.clone(), 1);
+        let kept = cache.key(Path::new("kept.rs"));
+        let removed = cache.key(Path::new("removed.rs"));
+        cache.insert(kept.clone(), test_stamp(1), 1, &[]);
+        cache.insert(removed.clone(), test_stamp(2), 2, &[]);
+        cache.save().unwrap();
+
+        let cache = TodoCache::load(cache_path.clone(), 1);
+        assert!(cache.get_unchanged(&kept, &test_stamp(1)).is_some());
+        cache.save().unwrap();
+
+        let cache = TodoCache::load(cache_path.clone(), 1);
+        assert!(cache.get_unchanged(&kept, &test_stamp(1)).is_some());
+        assert!(cache.get_unchanged(&removed, &test_stamp(2)).is_none());
+
+        fs::remove_file(cache_path).unwrap();
+    }
+}
@@ -1,7 +1,6 @@
 // Module for the structs that hold the comment types
 
-use regex::escape;
-use serde::{Deserialize, Deserializer};
+use serde::Deserialize;
 
 /// An enum for custom comment types.
 ///
@@ -35,11 +34,11 @@ impl CommentType {
         }
     }
 
-    /// Returns suffix token for comment.
-    pub fn suffix(&self) -> &str {
+    /// Returns suffix token for comment. Single-line comments have no suffix.
+    pub fn suffix(&self) -> Option<&str> {
         match self {
-            CommentType::SingleLine(_c) => "$",
-            CommentType::Block(c) => &c.suffix,
+            CommentType::SingleLine(_c) => None,
+            CommentType::Block(c) => Some(&c.suffix),
         }
     }
 }
@@ -50,14 +49,13 @@ impl CommentType {
 #[derive(Debug, Clone, Deserialize)]
 pub struct SingleLineComment {
     #[serde(rename = "single")]
-    #[serde(deserialize_with = "escape_deserialize")]
     prefix: String,
 }
 
 impl SingleLineComment {
     pub fn new(prefix: &str) -> SingleLineComment {
         SingleLineComment {
-            prefix: escape(prefix),
+            prefix: prefix.to_string(),
         }
     }
 }
@@ -73,17 +71,15 @@ impl Into<CommentType> for SingleLineComment {
 /// For Rust comments it should hold `/*` and `*/`.
 #[derive(Debug, Clone, Deserialize)]
 pub struct BlockComment {
-    #[serde(deserialize_with = "escape_deserialize")]
     prefix: String,
-    #[serde(deserialize_with = "escape_deserialize")]
     suffix: String,
 }
 
 impl BlockComment {
     pub fn new(prefix: &str, suffix: &str) -> BlockComment {
         BlockComment {
-            prefix: escape(prefix),
-            suffix: escape(suffix),
+            prefix: prefix.to_string(),
+            suffix: suffix.to_string(),
         }
     }
 }
@@ -94,19 +90,146 @@ impl Into<CommentType> for BlockComment {
     }
 }
 
-fn escape_deserialize<'de, D>(deserializer: D) -> Result<String, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let s: String = Deserialize::deserialize(deserializer)?;
-    Ok(escape(&s))
+/// An enum for custom string literal types.
+///
+/// Comment prefixes inside of string literals are not treated as comments.
+/// There are three types of string literals:
+///     Quoted: for strings like `"string"` that may contain escaped delimiters like `\"`
+///     Char: for character literals like `'c'` or `'\n'`
+///     Raw: for Rust style raw strings like `r#"string"#`
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum StringType {
+    Quoted(QuotedString),
+    Char(CharLiteral),
+    Raw(RawString),
+}
+
+impl StringType {
+    /// Creates new quoted string type that does not span multiple lines
+    pub fn new_quoted(delimiter: &str) -> StringType {
+        QuotedString::new(delimiter, false).into()
+    }
+
+    /// Creates new quoted string type that can span multiple lines
+    pub fn new_multiline_quoted(delimiter: &str) -> StringType {
+        QuotedString::new(delimiter, true).into()
+    }
+
+    /// Creates new character literal type
+    pub fn new_char(delimiter: &str) -> StringType {
+        CharLiteral::new(delimiter).into()
+    }
+
+    /// Creates new raw string type
+    pub fn new_raw(prefix: &str) -> StringType {
+        RawString::new(prefix).into()
+    }
+
+    /// Returns the token that starts the string literal.
+    pub fn prefix(&self) -> &str {
+        match self {
+            StringType::Quoted(s) => &s.delimiter,
+            StringType::Char(s) => &s.delimiter,
+            StringType::Raw(s) => &s.prefix,
+        }
+    }
+}
+
+/// Stores a quoted string type.
+/// The same delimiter starts and ends the string, and it can be escaped using `\`.
+/// For Rust strings it should hold `"`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuotedString {
+    #[serde(rename = "string")]
+    pub(crate) delimiter: String,
+    /// If false, backslashes do not escape the delimiter.
+    #[serde(default = "default_true")]
+    pub(crate) escape: bool,
+    /// If false, strings that are not closed on the same line are treated as code.
+    #[serde(default)]
+    pub(crate) multiline: bool,
+}
+
+impl QuotedString {
+    pub fn new(delimiter: &str, multiline: bool) -> QuotedString {
+        QuotedString {
+            delimiter: delimiter.to_string(),
+            escape: true,
+            multiline,
+        }
+    }
+}
+
+impl From<QuotedString> for StringType {
+    fn from(s: QuotedString) -> StringType {
+        StringType::Quoted(s)
+    }
+}
+
+/// Stores a character literal type.
+/// The literal has to hold exactly one character or escape sequence.
+/// Otherwise, the delimiter is treated as code so that things like Rust lifetimes are skipped.
+/// For Rust characters it should hold `'`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CharLiteral {
+    #[serde(rename = "char")]
+    pub(crate) delimiter: String,
+}
+
+impl CharLiteral {
+    pub fn new(delimiter: &str) -> CharLiteral {
+        CharLiteral {
+            delimiter: delimiter.to_string(),
+        }
+    }
+}
+
+impl From<CharLiteral> for StringType {
+    fn from(s: CharLiteral) -> StringType {
+        StringType::Char(s)
+    }
+}
+
+/// Stores a raw string type.
+/// Raw strings start with the prefix followed by any number of `#`s and a `"`.
+/// They end with a `"` followed by the same number of `#`s.
+/// For Rust raw strings it should hold `r`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RawString {
+    #[serde(rename = "raw_string")]
+    pub(crate) prefix: String,
+}
+
+impl RawString {
+    pub fn new(prefix: &str) -> RawString {
+        RawString {
+            prefix: prefix.to_string(),
+        }
+    }
+}
+
+impl From<RawString> for StringType {
+    fn from(s: RawString) -> StringType {
+        StringType::Raw(s)
+    }
+}
+
+fn default_true() -> bool {
+    true
 }
 
 /// Struct for storing a collection of CommentType enums that correspond to a specifix content type.
 /// It behaves as a wrapper for Vec<CommentType>.
+///
+/// It also holds the string literal types of the content type so that comment prefixes
+/// inside of strings can be skipped.
 #[derive(Debug, Default, Clone, Deserialize)]
 pub struct CommentTypes {
     comment_types: Vec<CommentType>,
+    #[serde(default)]
+    string_types: Vec<StringType>,
 }
 
 impl CommentTypes {
@@ -132,10 +255,22 @@ impl CommentTypes {
         self
     }
 
+    /// Adds a string literal type.
+    /// For Rust strings you might use `CommentTypes::new().add_string(StringType::new_quoted("\""))`
+    pub fn add_string(mut self, string_type: StringType) -> Self {
+        self.string_types.push(string_type);
+        self
+    }
+
     /// Returns an iterator over all of the comment types in the struct.
-    pub fn iter(&self) -> std::slice::Iter<CommentType> {
+    pub fn iter(&self) -> std::slice::Iter<'_, CommentType> {
         self.into_iter()
     }
+
+    /// Returns an iterator over all of the string literal types in the struct.
+    pub fn strings(&self) -> std::slice::Iter<'_, StringType> {
+        self.string_types.iter()
+    }
 }
 
 impl IntoIterator for CommentTypes {
@@ -158,6 +293,9 @@ impl<'a> IntoIterator for &'a CommentTypes {
 
 impl From<Vec<CommentType>> for CommentTypes {
     fn from(comment_types: Vec<CommentType>) -> CommentTypes {
-        CommentTypes { comment_types }
+        CommentTypes {
+            comment_types,
+            string_types: Vec::new(),
+        }
     }
 }
@@ -5,24 +5,48 @@ use failure::Error;
 use fnv::FnvHashMap;
 use serde::Deserialize;
 
-use crate::comments::{CommentType, CommentTypes};
+use crate::comments::{CommentType, CommentTypes, StringType};
 use crate::errors::TodoRError::InvalidConfigFile;
+use crate::format::Severity;
+use crate::template::{Template, TemplateFormat, TemplatePart};
 
 /// Comments configuration as read from the config file
 #[derive(Debug, Default, Clone, Deserialize)]
 pub(crate) struct CommentsConfig {
     ext: Option<String>,
     exts: Option<Vec<String>>,
-    types: Vec<CommentType>,
+    types: Vec<TypeConfig>,
 }
 
 impl CommentsConfig {
     /// Consume the CommentsConfig type and return its parts
     pub fn break_apart(self) -> (Option<String>, Option<Vec<String>>, CommentTypes) {
-        (self.ext, self.exts, self.types.into())
+        let mut comment_types = Vec::new();
+        let mut string_types = Vec::new();
+        for type_config in self.types {
+            match type_config {
+                TypeConfig::Comment(c) => comment_types.push(c),
+                TypeConfig::String(s) => string_types.push(s),
+            }
+        }
+
+        let types = string_types
+            .into_iter()
+            .fold(CommentTypes::from(comment_types), CommentTypes::add_string);
+
+        (self.ext, self.exts, types)
     }
 }
 
+/// Item of the `types` list in the comments config.
+/// It is either a comment type or a string literal type.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum TypeConfig {
+    Comment(CommentType),
+    String(StringType),
+}
+
 /// Style as read from the config file
 #[derive(Debug, Clone, Deserialize)]
 #[serde(untagged)]
@@ -144,6 +168,50 @@ impl StylesConfig {
     }
 }
 
+/// Output template as read from the `formats` config.
+/// A string is only rendered for each TODO.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum FormatConfig {
+    Todo(String),
+    Parts {
+        todo: String,
+        header: Option<String>,
+        footer: Option<String>,
+        file_header: Option<String>,
+        file_footer: Option<String>,
+    },
+}
+
+impl FormatConfig {
+    /// Converts FormatConfig into TemplateFormat type
+    pub fn into_template_format(self) -> Result<TemplateFormat, Error> {
+        let parse = |template: Option<String>, part| {
+            template
+                .map(|template| Template::parse_part(&template, part))
+                .transpose()
+        };
+        let format = match self {
+            FormatConfig::Todo(todo) => TemplateFormat::new(Template::parse(&todo)?),
+            FormatConfig::Parts {
+                todo,
+                header,
+                footer,
+                file_header,
+                file_footer,
+            } => TemplateFormat {
+                todo: Template::parse(&todo)?,
+                header: parse(header, TemplatePart::Count)?,
+                footer: parse(footer, TemplatePart::Count)?,
+                file_header: parse(file_header, TemplatePart::File)?,
+                file_footer: parse(file_footer, TemplatePart::File)?,
+            },
+        };
+
+        Ok(format)
+    }
+}
+
 /// TodoR configuration settings as read from the config file
 #[derive(Debug, Default, Clone, Deserialize)]
 pub(crate) struct TodoRConfigFileSerial {
@@ -156,9 +224,17 @@ pub(crate) struct TodoRConfigFileSerial {
     #[serde(default)]
     pub default_ext: String,
     #[serde(default)]
+    pub trailing_comments: bool,
+    #[serde(default)]
+    pub cache: bool,
+    #[serde(default)]
     pub default_comments: Vec<CommentsConfig>,
     #[serde(default)]
     pub comments: Vec<CommentsConfig>,
     #[serde(default)]
     pub styles: StylesConfig,
+    #[serde(default)]
+    pub severity: FnvHashMap<String, Severity>,
+    #[serde(default)]
+    pub formats: FnvHashMap<String, FormatConfig>,
 }
@@ -1,6 +1,6 @@
 // Module for creating regexs for custom tags
 
-use regex::Regex;
+use regex::{escape, Regex};
 use std::borrow::Borrow;
 
 use crate::comments::CommentType;
@@ -28,14 +28,70 @@ where
 
     Regex::new(&format!(
         r"(?i)^\s*{}\s*({})\s?{}[:\s]?(?:\s+{})?\s*{}", // whitespace and optional colon
-        comment_type.prefix(),                          // comment prefix token
+        escape(comment_type.prefix()),                  // comment prefix token
         tags_string,                                    // custom tags
         r"(?:\(@?(\S+)\))?",                            // optional user tag in ()`s
         r"(.*?)",                                       // content
-        comment_type.suffix(),                          // comment suffix token
+        suffix_regex_string(comment_type),              // comment suffix token
     ))
 }
 
+/// Returns Regex that matches a TODO on a line inside of an open block comment.
+///
+/// The line does not need to start with the comment prefix but it may start with `*`s
+/// that are commonly used to decorate block comments.
+///
+/// The capture groups are the same as the ones in `get_regex_for_comment()`.
+pub(crate) fn get_regex_for_block_content<S>(
+    custom_tags: &[S],
+    comment_type: &CommentType,
+) -> Result<Regex, regex::Error>
+where
+    S: Borrow<str>,
+{
+    let tags_string = custom_tags.join("|");
+
+    Regex::new(&format!(
+        r"(?i)^\s*\**\s*({})\s?{}[:\s]?(?:\s+{})?\s*{}",
+        tags_string,
+        r"(?:\(@?(\S+)\))?",
+        r"(.*?)",
+        suffix_regex_string(comment_type),
+    ))
+}
+
+/// Returns Regex that matches the text of a comment line that may continue a TODO.
+///
+/// If `in_block` is true, the Regex matches a line inside of an open block comment.
+/// Otherwise, the line has to start with the comment prefix.
+///
+/// The capture group in the Regex is the text of the comment.
+pub(crate) fn get_regex_for_continuation(
+    comment_type: &CommentType,
+    in_block: bool,
+) -> Result<Regex, regex::Error> {
+    let prefix = if in_block {
+        r"(?:\*+(?:\s+|$))?".to_string()
+    } else {
+        escape(comment_type.prefix())
+    };
+
+    Regex::new(&format!(
+        r"^\s*{}\s*(.*?)\s*{}",
+        prefix,
+        suffix_regex_string(comment_type),
+    ))
+}
+
+/// Returns the regex string for the end of a comment.
+/// Block comments can also end at the end of the line since they may continue on the next line.
+fn suffix_regex_string(comment_type: &CommentType) -> String {
+    match comment_type.suffix() {
+        Some(suffix) => format!("(?:{}|$)", escape(suffix)),
+        None => "$".to_string(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -187,6 +243,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn regex_slashstar_comment_unclosed() {
+        test_regex(
+            "/* todo: item continues \t ",
+            Some("item continues"),
+            &CommentType::new_block("/*", "*/"),
+        );
+    }
+
+    #[test]
+    fn regex_block_content() {
+        let re =
+            get_regex_for_block_content(&["TODO"], &CommentType::new_block("/*", "*/")).unwrap();
+        let caps = re.captures("   * todo(user): item */ ").unwrap();
+        assert_eq!("user", &caps[2]);
+        assert_eq!("item", &caps[3]);
+    }
+
+    #[test]
+    fn regex_block_content_no_star() {
+        let re =
+            get_regex_for_block_content(&["TODO"], &CommentType::new_block("\"\"\"", "\"\"\""))
+                .unwrap();
+        let caps = re.captures("    todo: item").unwrap();
+        assert_eq!("item", &caps[3]);
+        assert!(re.captures("    not a todo: item").is_none());
+    }
+
+    #[test]
+    fn regex_continuation() {
+        let re = get_regex_for_continuation(&CommentType::new_single("//"), false).unwrap();
+        let caps = re.captures("  //    more text  ").unwrap();
+        assert_eq!("more text", &caps[1]);
+        assert_eq!(8, caps.get(1).unwrap().start());
+        assert!(re.captures("code(); // more text").is_none());
+    }
+
+    #[test]
+    fn regex_continuation_block() {
+        let re = get_regex_for_continuation(&CommentType::new_block("/*", "*/"), true).unwrap();
+        let caps = re.captures("  *    more text */ code();").unwrap();
+        assert_eq!("more text", &caps[1]);
+        let caps = re.captures("  */").unwrap();
+        assert_eq!("", &caps[1]);
+    }
+
     #[test]
     fn regex_comment_not_on_separate_line() {
         test_regex(
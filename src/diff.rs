@@ -0,0 +1,554 @@
+// Module for comparing two saved TODO reports
+
+use failure::Error;
+use fnv::FnvHashSet;
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fmt::Write as StringWrite;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use crate::errors::TodoRError;
+use crate::todo::{count_occurrences, PathedTodo, Todo};
+
+/// Edits that share less than this fraction of words are treated as a resolved and an added TODO.
+const MIN_EDIT_SIMILARITY: f64 = 0.5;
+
+/// A TODO read from a report along with the file it is in.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ReportTodo {
+    pub file: PathBuf,
+    #[serde(flatten)]
+    pub todo: Todo,
+}
+
+impl Serialize for ReportTodo {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        PathedTodo {
+            file: &self.file,
+            todo: &self.todo,
+            root: None,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Reads a report written with the `json` or `prettyjson` format.
+pub fn read_report(reader: impl Read) -> Result<Vec<ReportTodo>, Error> {
+    let mut report: Vec<ReportTodo> =
+        serde_json::from_reader(reader).map_err(|err| TodoRError::InvalidReport {
+            message: err.to_string(),
+        })?;
+
+    // occurrences are not written in reports so they are counted again for each file
+    let files: FnvHashSet<PathBuf> = report.iter().map(|rtodo| rtodo.file.clone()).collect();
+    for file in files {
+        let mut todos: Vec<Todo> = report
+            .iter()
+            .filter(|rtodo| rtodo.file == file)
+            .map(|rtodo| rtodo.todo.clone())
+            .collect();
+        count_occurrences(&mut todos);
+
+        let occurrences = todos.into_iter().map(|todo| todo.occurrence);
+        let rtodos = report.iter_mut().filter(|rtodo| rtodo.file == file);
+        for (rtodo, occurrence) in rtodos.zip(occurrences) {
+            rtodo.todo.occurrence = occurrence;
+        }
+    }
+
+    Ok(report)
+}
+
+/// A TODO that is in both reports but changed.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangedTodo {
+    pub old: ReportTodo,
+    pub new: ReportTodo,
+}
+
+/// Changes to TODOs between an old and a new report.
+///
+/// TODOs with the same tag and content that moved to a different line or file are `moved`.
+/// Nearby TODOs that only had the users in them changed are `reassigned`,
+/// and ones whose tag or content changed are `edited`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TodoDiff {
+    pub added: Vec<ReportTodo>,
+    pub resolved: Vec<ReportTodo>,
+    pub moved: Vec<ChangedTodo>,
+    pub edited: Vec<ChangedTodo>,
+    pub reassigned: Vec<ChangedTodo>,
+}
+
+impl TodoDiff {
+    /// Matches the TODOs in old to the TODOs in new and sorts out what changed.
+    pub fn new(old: &[ReportTodo], new: &[ReportTodo]) -> TodoDiff {
+        let mut matcher = Matcher {
+            old,
+            new,
+            old_matched: vec![None; old.len()],
+            new_matched: vec![false; new.len()],
+        };
+
+        // Unchanged TODOs in the same file come first so they can be used to track line shifts
+        matcher.match_pairs(|o, n| {
+            let same = o.file == n.file && same_text(&o.todo, &n.todo);
+            same.then(|| Reverse(line_distance(o.todo.line, n.todo.line)))
+        });
+        matcher.match_pairs(|o, n| same_text(&o.todo, &n.todo).then_some(()));
+
+        let old_pairs = matcher.pairs();
+        matcher.match_pairs(|o, n| {
+            if o.file != n.file {
+                return None;
+            }
+
+            let similarity = if o.todo.tag == n.todo.tag && same_text_without_users(o, n) {
+                // reassigned TODOs are always matched first
+                2.0
+            } else {
+                word_similarity(&o.todo, &n.todo)
+            };
+            let expected_line = expected_new_line(o, &old_pairs);
+            // a TODO rewritten where it was is still the same TODO if it kept its tag
+            let in_place = o.todo.tag == n.todo.tag && expected_line == Some(n.todo.line);
+
+            if similarity < MIN_EDIT_SIMILARITY && !in_place {
+                return None;
+            }
+            let distance = expected_line.map_or(usize::MAX, |l| line_distance(l, n.todo.line));
+
+            // floats do not implement Ord so the similarity is scaled into an integer
+            Some(((similarity * 1000.0) as u64, Reverse(distance)))
+        });
+
+        let mut diff = TodoDiff::default();
+        for (i, matched) in matcher.old_matched.iter().enumerate() {
+            let o = &old[i];
+            let n = match matched {
+                Some(j) => &new[*j],
+                None => {
+                    diff.resolved.push(o.clone());
+                    continue;
+                }
+            };
+
+            let change = ChangedTodo {
+                old: o.clone(),
+                new: n.clone(),
+            };
+            if same_text(&o.todo, &n.todo) {
+                if o.file != n.file || o.todo.line != n.todo.line {
+                    diff.moved.push(change);
+                }
+            } else if o.todo.tag == n.todo.tag && same_text_without_users(o, n) {
+                diff.reassigned.push(change);
+            } else {
+                diff.edited.push(change);
+            }
+        }
+
+        diff.added.extend(
+            new.iter()
+                .zip(&matcher.new_matched)
+                .filter(|(_, &matched)| !matched)
+                .map(|(n, _)| n.clone()),
+        );
+
+        diff
+    }
+
+    /// Returns true if no TODOs changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.resolved.is_empty()
+            && self.moved.is_empty()
+            && self.edited.is_empty()
+            && self.reassigned.is_empty()
+    }
+
+    /// Writes the changes as plain text.
+    pub fn write_text(&self, out_buffer: &mut impl Write) -> Result<(), Error> {
+        writeln!(
+            out_buffer,
+            "{} added, {} resolved, {} moved, {} edited, {} reassigned",
+            self.added.len(),
+            self.resolved.len(),
+            self.moved.len(),
+            self.edited.len(),
+            self.reassigned.len(),
+        )?;
+
+        let sections = [("Added", &self.added), ("Resolved", &self.resolved)];
+        for (title, rtodos) in sections.iter() {
+            if rtodos.is_empty() {
+                continue;
+            }
+
+            writeln!(out_buffer, "\n{}:", title)?;
+            for rtodo in rtodos.iter() {
+                writeln!(
+                    out_buffer,
+                    "  {}:{}  {}  {}",
+                    rtodo.file.display(),
+                    rtodo.todo.line,
+                    rtodo.todo.tag,
+                    rtodo.todo.content,
+                )?;
+            }
+        }
+
+        let sections = [
+            ("Moved", &self.moved),
+            ("Edited", &self.edited),
+            ("Reassigned", &self.reassigned),
+        ];
+        for (title, changes) in sections.iter() {
+            if changes.is_empty() {
+                continue;
+            }
+
+            writeln!(out_buffer, "\n{}:", title)?;
+            for change in changes.iter() {
+                let (old, new) = (&change.old, &change.new);
+                writeln!(
+                    out_buffer,
+                    "  {}:{} -> {}:{}",
+                    old.file.display(),
+                    old.todo.line,
+                    new.file.display(),
+                    new.todo.line,
+                )?;
+
+                if same_text(&old.todo, &new.todo) {
+                    writeln!(out_buffer, "    {}  {}", new.todo.tag, new.todo.content)?;
+                } else {
+                    writeln!(out_buffer, "    - {}  {}", old.todo.tag, old.todo.content)?;
+                    writeln!(out_buffer, "    + {}  {}", new.todo.tag, new.todo.content)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the changes as markdown tables that can be pasted in release notes.
+    pub fn write_markdown(&self, out_buffer: &mut impl Write) -> Result<(), Error> {
+        let sections = [("Added", &self.added), ("Resolved", &self.resolved)];
+        for (title, rtodos) in sections.iter() {
+            if rtodos.is_empty() {
+                continue;
+            }
+
+            let mut table_string = format!(
+                "### {}\n| Filename | line | type | content |\n|:---|:---:|:---:|:---|\n",
+                title
+            );
+            for rtodo in rtodos.iter() {
+                writeln!(
+                    table_string,
+                    "| {} | {} | {} | {} |",
+                    rtodo.file.display(),
+                    rtodo.todo.line,
+                    rtodo.todo.tag,
+                    rtodo.todo.content,
+                )?;
+            }
+            writeln!(out_buffer, "{}", table_string)?;
+        }
+
+        if !self.moved.is_empty() {
+            let mut table_string =
+                "### Moved\n| From | To | type | content |\n|:---|:---|:---:|:---|\n".to_string();
+            for change in &self.moved {
+                writeln!(
+                    table_string,
+                    "| {}:{} | {}:{} | {} | {} |",
+                    change.old.file.display(),
+                    change.old.todo.line,
+                    change.new.file.display(),
+                    change.new.todo.line,
+                    change.new.todo.tag,
+                    change.new.todo.content,
+                )?;
+            }
+            writeln!(out_buffer, "{}", table_string)?;
+        }
+
+        let sections = [("Edited", &self.edited), ("Reassigned", &self.reassigned)];
+        for (title, changes) in sections.iter() {
+            if changes.is_empty() {
+                continue;
+            }
+
+            let mut table_string = format!(
+                "### {}\n| Filename | line | type | old content | new content |\n|:---|:---:|:---:|:---|:---|\n",
+                title
+            );
+            for change in changes.iter() {
+                writeln!(
+                    table_string,
+                    "| {} | {} | {} | {} | {} |",
+                    change.new.file.display(),
+                    change.new.todo.line,
+                    change.new.todo.tag,
+                    change.old.todo.content,
+                    change.new.todo.content,
+                )?;
+            }
+            writeln!(out_buffer, "{}", table_string)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the changes serialized in the JSON format.
+    pub fn write_json(&self, out_buffer: &mut impl Write) -> Result<(), Error> {
+        serde_json::to_writer_pretty(out_buffer, self)?;
+        Ok(())
+    }
+}
+
+/// Keeps track of which TODOs in the old and new reports have been matched.
+struct Matcher<'a> {
+    old: &'a [ReportTodo],
+    new: &'a [ReportTodo],
+    /// index of the new TODO each old TODO was matched to
+    old_matched: Vec<Option<usize>>,
+    new_matched: Vec<bool>,
+}
+
+impl<'a> Matcher<'a> {
+    /// Matches unmatched TODOs where score gives Some, best scores first.
+    /// Ties keep the order of the reports.
+    fn match_pairs<F, S>(&mut self, score: F)
+    where
+        F: Fn(&ReportTodo, &ReportTodo) -> Option<S>,
+        S: Ord,
+    {
+        let mut candidates = Vec::new();
+        for (i, o) in self.old.iter().enumerate() {
+            if self.old_matched[i].is_some() {
+                continue;
+            }
+
+            for (j, n) in self.new.iter().enumerate() {
+                if self.new_matched[j] {
+                    continue;
+                }
+
+                if let Some(s) = score(o, n) {
+                    candidates.push((s, i, j));
+                }
+            }
+        }
+
+        // stable sort so earlier TODOs win ties
+        candidates.sort_by(|a, b| b.0.cmp(&a.0));
+        for (_, i, j) in candidates {
+            if self.old_matched[i].is_none() && !self.new_matched[j] {
+                self.old_matched[i] = Some(j);
+                self.new_matched[j] = true;
+            }
+        }
+    }
+
+    /// Returns the matched TODOs as (old, new) pairs.
+    fn pairs(&self) -> Vec<(&'a ReportTodo, &'a ReportTodo)> {
+        self.old_matched
+            .iter()
+            .enumerate()
+            .filter_map(|(i, matched)| {
+                let (old, new) = (self.old, self.new);
+                matched.map(|j| (&old[i], &new[j]))
+            })
+            .collect()
+    }
+}
+
+/// Guesses the line old would be on in the new report using the closest matched TODO above it
+/// in the same file. Lines before the first match are not shifted.
+fn expected_new_line(old: &ReportTodo, pairs: &[(&ReportTodo, &ReportTodo)]) -> Option<usize> {
+    let shift = pairs
+        .iter()
+        .filter(|(o, n)| o.file == old.file && n.file == old.file && o.todo.line < old.todo.line)
+        .max_by_key(|(o, _)| o.todo.line)
+        .map_or(0, |(o, n)| n.todo.line as isize - o.todo.line as isize);
+
+    let line = old.todo.line as isize + shift;
+    if line > 0 {
+        Some(line as usize)
+    } else {
+        None
+    }
+}
+
+fn line_distance(a: usize, b: usize) -> usize {
+    a.max(b) - a.min(b)
+}
+
+fn same_text(a: &Todo, b: &Todo) -> bool {
+    a.tag == b.tag && a.normalized_content() == b.normalized_content()
+}
+
+/// Returns true if the content of the TODOs is the same once users are taken out.
+fn same_text_without_users(a: &ReportTodo, b: &ReportTodo) -> bool {
+    words_without_users(&a.todo) == words_without_users(&b.todo)
+}
+
+fn words_without_users(todo: &Todo) -> Vec<&str> {
+    todo.content
+        .split_whitespace()
+        .filter(|word| !word.starts_with('@'))
+        .collect()
+}
+
+/// Returns the fraction of distinct words that are in both TODOs.
+fn word_similarity(a: &Todo, b: &Todo) -> f64 {
+    let a_words: FnvHashSet<String> = a
+        .content
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect();
+    let b_words: FnvHashSet<String> = b
+        .content
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect();
+
+    let all_words = a_words.union(&b_words).count();
+    if all_words == 0 {
+        return 1.0;
+    }
+
+    a_words.intersection(&b_words).count() as f64 / all_words as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rtodo(file: &str, line: usize, tag: &str, content: &str) -> ReportTodo {
+        ReportTodo {
+            file: PathBuf::from(file),
+            todo: Todo::new(line, tag, content),
+        }
+    }
+
+    #[test]
+    fn read_json_report() {
+        let report = r#"[
+            {"file":"a.rs","id":"ff64d13fe333","line":2,"tag":"TODO","text":"item","users":[]},
+            {"file":"a.rs","line":5,"end_line":6,"tag":"TODO","text":"item","users":[]}
+        ]"#;
+        let report = read_report(report.as_bytes()).unwrap();
+
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[1].todo.end_line, 6);
+        assert_eq!(report[1].todo.occurrence, 1);
+        assert!(read_report("{}".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn unchanged() {
+        let old = vec![rtodo("a.rs", 2, "TODO", "item")];
+        let diff = TodoDiff::new(&old, &old);
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn added_and_resolved() {
+        let old = vec![rtodo("a.rs", 2, "TODO", "fix parser")];
+        let new = vec![rtodo("b.rs", 9, "TODO", "write docs")];
+        let diff = TodoDiff::new(&old, &new);
+
+        assert_eq!(diff.resolved, old);
+        assert_eq!(diff.added, new);
+        assert!(diff.edited.is_empty());
+    }
+
+    #[test]
+    fn moved() {
+        let old = vec![
+            rtodo("a.rs", 2, "TODO", "item"),
+            rtodo("a.rs", 8, "TODO", "other"),
+        ];
+        let new = vec![
+            rtodo("a.rs", 12, "TODO", "item"),
+            rtodo("b.rs", 1, "TODO", "other"),
+        ];
+        let diff = TodoDiff::new(&old, &new);
+
+        assert_eq!(diff.moved.len(), 2);
+        assert_eq!(diff.moved[0].new.todo.line, 12);
+        assert_eq!(diff.moved[1].new.file, PathBuf::from("b.rs"));
+        assert!(diff.added.is_empty() && diff.resolved.is_empty());
+    }
+
+    #[test]
+    fn edited_and_reassigned() {
+        let old = vec![
+            rtodo("a.rs", 1, "TODO", "keep"),
+            rtodo("a.rs", 3, "TODO", "@ann fix the parser"),
+            rtodo("a.rs", 5, "TODO", "completely different"),
+        ];
+        let new = vec![
+            rtodo("a.rs", 2, "TODO", "keep"),
+            rtodo("a.rs", 4, "TODO", "@bob fix the parser"),
+            // edited in place even though the words changed
+            rtodo("a.rs", 6, "TODO", "rewritten text"),
+            rtodo("a.rs", 7, "FIXME", "not the same"),
+        ];
+        let diff = TodoDiff::new(&old, &new);
+
+        assert_eq!(diff.reassigned.len(), 1);
+        assert_eq!(diff.reassigned[0].new.todo.content, "@bob fix the parser");
+        assert_eq!(diff.edited.len(), 1);
+        assert_eq!(diff.edited[0].old.todo.content, "completely different");
+        assert_eq!(diff.moved.len(), 1);
+        assert_eq!(diff.added.len(), 1);
+    }
+
+    #[test]
+    fn duplicates_track_closest_line() {
+        let old = vec![
+            rtodo("a.rs", 2, "TODO", "item"),
+            rtodo("a.rs", 9, "TODO", "item"),
+        ];
+        let new = vec![rtodo("a.rs", 9, "TODO", "item")];
+        let diff = TodoDiff::new(&old, &new);
+
+        assert!(diff.moved.is_empty());
+        assert_eq!(diff.resolved.len(), 1);
+        assert_eq!(diff.resolved[0].todo.line, 2);
+    }
+
+    #[test]
+    fn text_output() {
+        let old = vec![rtodo("a.rs", 2, "TODO", "item")];
+        let new = vec![
+            rtodo("a.rs", 3, "TODO", "item"),
+            rtodo("b.rs", 1, "FIXME", "new"),
+        ];
+
+        let mut out: Vec<u8> = Vec::new();
+        TodoDiff::new(&old, &new).write_text(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "1 added, 0 resolved, 1 moved, 0 edited, 0 reassigned
+
+Added:
+  b.rs:1  FIXME  new
+
+Moved:
+  a.rs:2 -> a.rs:3
+    TODO  item
+"
+        );
+    }
+}
@@ -4,9 +4,10 @@ use ansi_term::Style;
 use failure::Error;
 use log::debug;
 use std::io::Write;
+use std::path::Path;
 
 use crate::maps::FallbackHashMap;
-use crate::todo::TodoFile;
+use crate::todo::{todo_id, TodoFile};
 
 /// Struct for holding ansi color printing options
 #[derive(Debug, Clone)]
@@ -15,6 +16,8 @@ pub struct TodoRStyles {
     pub line_number_style: Style,
     pub user_style: Style,
     pub content_style: Style,
+    pub blame_style: Style,
+    pub id_style: Style,
     tag_styles: FallbackHashMap<String, Style>,
 }
 
@@ -32,6 +35,8 @@ impl TodoRStyles {
             line_number_style,
             user_style,
             content_style,
+            blame_style: Style::new().dimmed(),
+            id_style: Style::new().dimmed(),
             tag_styles: FallbackHashMap::new(default_tag_style),
         }
     }
@@ -43,6 +48,8 @@ impl TodoRStyles {
             line_number_style: Style::new(),
             user_style: Style::new(),
             content_style: Style::new(),
+            blame_style: Style::new(),
+            id_style: Style::new(),
             tag_styles: FallbackHashMap::new(Style::new()),
         }
     }
@@ -60,15 +67,17 @@ impl TodoRStyles {
 }
 
 /// Writes file path and a list of Todos to out_buffer.
+/// The ID of each TODO is written after it if show_ids is true. IDs are made relative to root.
 ///
 /// If no there are no `Todo`s that satisfy `pred` in `todo_file`, nothing is printed.
 pub fn write_file_todos(
     out_buffer: &mut impl Write,
     todo_file: &TodoFile,
     styles: &TodoRStyles,
+    show_ids: bool,
+    root: Option<&Path>,
 ) -> Result<(), Error> {
-    let mut todos = todo_file.todos.iter().peekable();
-    if todos.peek().is_some() {
+    if !todo_file.is_empty() {
         writeln!(
             out_buffer,
             "{}",
@@ -77,8 +86,13 @@ pub fn write_file_todos(
                 .paint(todo_file.filepath.to_string_lossy())
         )?;
 
-        for todo in todos {
-            todo.write_style_string(out_buffer, styles)?;
+        for todo in &todo_file.todos {
+            let id = if show_ids {
+                Some(todo_id(&todo_file.filepath, todo, root))
+            } else {
+                None
+            };
+            todo.write_style_string_with_id(out_buffer, styles, id.as_deref())?;
         }
     } else {
         debug!(
@@ -1,13 +1,19 @@
 // Module for printing TODOs in various formats
 
+use crate::errors::TodoRError;
+use crate::todo::{root_relative_path, PathedTodo};
 use crate::TodoR;
 use failure::Error;
 use fnv::FnvHashMap;
-use serde_json;
+use serde::Deserialize;
+use serde_json::{self, json};
+use std::borrow::Cow;
 use std::fmt::Write as StringWrite;
 use std::io::{self, Write};
+use std::path::{Component, Path};
 
 use crate::display::{write_file_todos, TodoRStyles};
+use crate::todo::TodoFile;
 
 // MAYB: add more output formats
 /// Enum holding the different supported output formats.
@@ -17,9 +23,229 @@ pub enum ReportFormat {
     Markdown,
     UserMarkdown,
     Csv,
+    Tsv,
+    Sarif,
+    GitHub,
+    GitLab,
+    JUnit,
+    Checkstyle,
+    Template,
     Default,
 }
 
+/// Field of a TODO that can be written as a column by the `csv` and `tsv` formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    File,
+    Line,
+    EndLine,
+    Tag,
+    Text,
+    Users,
+    Id,
+    Author,
+    AuthorEmail,
+    Commit,
+    Date,
+}
+
+impl Column {
+    /// Parses a Column from its name, like `file` or `author_email`.
+    pub fn from_name(name: &str) -> Result<Column, Error> {
+        let column = match name.trim() {
+            "file" => Column::File,
+            "line" => Column::Line,
+            "end_line" => Column::EndLine,
+            "tag" => Column::Tag,
+            "text" => Column::Text,
+            "users" => Column::Users,
+            "id" => Column::Id,
+            "author" => Column::Author,
+            "author_email" => Column::AuthorEmail,
+            "commit" => Column::Commit,
+            "date" => Column::Date,
+            _ => {
+                return Err(TodoRError::InvalidColumn {
+                    column: name.to_string(),
+                }
+                .into())
+            }
+        };
+
+        Ok(column)
+    }
+
+    /// Returns the name of the Column that is used in headers.
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::File => "file",
+            Column::Line => "line",
+            Column::EndLine => "end_line",
+            Column::Tag => "tag",
+            Column::Text => "text",
+            Column::Users => "users",
+            Column::Id => "id",
+            Column::Author => "author",
+            Column::AuthorEmail => "author_email",
+            Column::Commit => "commit",
+            Column::Date => "date",
+        }
+    }
+
+    /// Gets the value of the Column for ptodo.
+    /// Blame columns are empty if the TODO has no blame information.
+    pub(crate) fn value(self, ptodo: &PathedTodo) -> String {
+        let todo = ptodo.todo;
+        let blame = todo.blame.as_ref();
+        match self {
+            Column::File => ptodo.file.display().to_string(),
+            Column::Line => todo.line.to_string(),
+            Column::EndLine => todo.end_line.to_string(),
+            Column::Tag => todo.tag.clone(),
+            Column::Text => todo.content.clone(),
+            Column::Users => todo.users().join(" "),
+            Column::Id => ptodo.id(),
+            Column::Author => blame.map(|b| b.author.clone()).unwrap_or_default(),
+            Column::AuthorEmail => blame.map(|b| b.author_email.clone()).unwrap_or_default(),
+            Column::Commit => blame.map(|b| b.commit.clone()).unwrap_or_default(),
+            Column::Date => blame.map(|b| b.date.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+/// Quotes field for CSV following RFC 4180 if it has a comma, quote or line break.
+pub(crate) fn escape_csv(field: &str) -> Cow<'_, str> {
+    if field.contains(&[',', '"', '\r', '\n'][..]) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Escapes tabs, line breaks and backslashes in field for TSV
+/// since fields cannot hold them or be quoted.
+pub(crate) fn escape_tsv(field: &str) -> Cow<'_, str> {
+    if field.contains(&['\\', '\t', '\r', '\n'][..]) {
+        Cow::Owned(
+            field
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\r', "\\r")
+                .replace('\n', "\\n"),
+        )
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// How serious a TODO is in formats read by other tools. This is set per tag in the config.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Note,
+    #[default]
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// Returns the lowercase name of the Severity as used in the config.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// Turns filepath into a URI reference using `/` separators.
+/// Characters that are not allowed in URI paths are percent-encoded.
+fn path_uri(filepath: &Path) -> String {
+    let parts: Vec<String> = filepath
+        .components()
+        .filter_map(|component| match component {
+            Component::RootDir => None,
+            // drive letters like `C:` are kept as is
+            Component::Prefix(prefix) => Some(prefix.as_os_str().to_string_lossy().into_owned()),
+            c => Some(percent_encode(&c.as_os_str().to_string_lossy())),
+        })
+        .collect();
+
+    if filepath.has_root() {
+        format!("file:///{}", parts.join("/"))
+    } else {
+        parts.join("/")
+    }
+}
+
+/// Joins the components of filepath with `/` separators no matter the platform.
+fn slash_path(filepath: &Path) -> String {
+    let mut path = String::new();
+    for component in filepath.components() {
+        match component {
+            Component::RootDir => path.push('/'),
+            c => {
+                if !path.is_empty() && !path.ends_with('/') {
+                    path.push('/');
+                }
+                path.push_str(&c.as_os_str().to_string_lossy());
+            }
+        }
+    }
+    path
+}
+
+/// Escapes s for the message of a GitHub Actions workflow command.
+fn escape_workflow_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes s for a property of a GitHub Actions workflow command.
+fn escape_workflow_property(s: &str) -> String {
+    escape_workflow_data(s)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+/// Escapes s for XML text and attribute values.
+/// Characters that XML does not allow, like most control characters, are replaced with U+FFFD.
+pub(crate) fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // keep whitespace from being normalized in attribute values
+            '\t' => escaped.push_str("&#9;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            '\u{0}'..='\u{1F}' | '\u{FFFE}' | '\u{FFFF}' => escaped.push('\u{FFFD}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Percent-encodes every byte of s that is not allowed in a segment of a URI path.
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for &b in s.as_bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => encoded.push(b as char),
+            b'-' | b'.' | b'_' | b'~' | b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+'
+            | b',' | b';' | b'=' | b'@' => encoded.push(b as char),
+            _ => write!(encoded, "%{:02X}", b).unwrap(),
+        }
+    }
+    encoded
+}
+
 impl TodoR {
     /// Writes TODOs in TodoR serialized in the JSON format
     fn write_json(&self, out_buffer: &mut impl Write) -> Result<(), Error> {
@@ -42,17 +268,18 @@ impl TodoR {
             let todo = ptodo.todo;
             let table_string = tag_tables.entry(&todo.tag).or_insert_with(|| {
                 format!(
-                    "### {}s\n| Filename | line | {} |\n|:---|:---:|:---|\n",
+                    "### {}s\n| Filename | line | {} | id |\n|:---|:---:|:---|:---|\n",
                     todo.tag, todo.tag,
                 )
             });
 
             writeln!(
                 table_string,
-                "| {} | {} | {} |",
+                "| {} | {} | {} | {} |",
                 ptodo.file.display(),
                 todo.line,
                 todo.content,
+                ptodo.id(),
             )?;
         }
 
@@ -76,28 +303,30 @@ impl TodoR {
             if users.is_empty() {
                 writeln!(
                     untagged_todos_string,
-                    "| {} | {} | {} | {} |",
+                    "| {} | {} | {} | {} | {} |",
                     ptodo.file.display(),
                     todo.line,
                     todo.tag,
                     todo.content,
+                    ptodo.id(),
                 )?;
             } else {
                 for user in users {
                     let table_string = user_tables.entry(user).or_insert_with(|| {
                         format!(
-                            "### {}\n| Filename | line | type | content |\n|:---|:---:|:---:|:---|\n",
+                            "### {}\n| Filename | line | type | content | id |\n|:---|:---:|:---:|:---|:---|\n",
                             user,
                         )
                     });
 
                     writeln!(
                         table_string,
-                        "| {} | {} | {} | {} |",
+                        "| {} | {} | {} | {} | {} |",
                         ptodo.file.display(),
                         todo.line,
                         todo.tag,
                         todo.content,
+                        ptodo.id(),
                     )?;
                 }
             }
@@ -110,7 +339,7 @@ impl TodoR {
         if !untagged_todos_string.is_empty() {
             writeln!(
                 out_buffer,
-                "### Untagged\n| Filename | line | type | content |\n|:---|:---:|:---:|:---|\n{}",
+                "### Untagged\n| Filename | line | type | content | id |\n|:---|:---:|:---:|:---|:---|\n{}",
                 untagged_todos_string,
             )?;
         }
@@ -118,31 +347,336 @@ impl TodoR {
         Ok(())
     }
 
-    /// Writes TODOs in TodoR serialized in a csv format
+    /// Gets the columns for the `csv` and `tsv` formats.
+    /// Unless they were set, blame columns are added if any TODO has blame information.
+    fn columns(&self) -> Vec<Column> {
+        if let Some(columns) = &self.writer_config.columns {
+            return columns.clone();
+        }
+
+        let mut columns = vec![
+            Column::File,
+            Column::Line,
+            Column::Tag,
+            Column::Text,
+            Column::Id,
+        ];
+        if self.iter().any(|ptodo| ptodo.todo.blame.is_some()) {
+            columns.extend(&[
+                Column::Author,
+                Column::AuthorEmail,
+                Column::Commit,
+                Column::Date,
+            ]);
+        }
+        columns
+    }
+
+    /// Writes a header and a row for every TODO with fields escaped by escape and
+    /// separated by separator.
+    fn write_rows(
+        &self,
+        out_buffer: &mut impl Write,
+        separator: &str,
+        line_end: &str,
+        escape: impl Fn(&str) -> Cow<'_, str>,
+    ) -> Result<(), Error> {
+        let columns = self.columns();
+        let header: Vec<&str> = columns.iter().map(|column| column.name()).collect();
+        write!(out_buffer, "{}{}", header.join(separator), line_end)?;
+
+        for ptodo in self.iter() {
+            let row: Vec<String> = columns
+                .iter()
+                .map(|column| escape(&column.value(&ptodo)).into_owned())
+                .collect();
+            write!(out_buffer, "{}{}", row.join(separator), line_end)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes TODOs in TodoR as comma separated values following RFC 4180.
     fn write_csv(&self, out_buffer: &mut impl Write) -> Result<(), Error> {
-        writeln!(out_buffer, "Filename, line, type, content")?;
+        self.write_rows(out_buffer, ",", "\r\n", escape_csv)
+    }
 
+    /// Writes TODOs in TodoR as tab separated values.
+    fn write_tsv(&self, out_buffer: &mut impl Write) -> Result<(), Error> {
+        self.write_rows(out_buffer, "\t", "\n", escape_tsv)
+    }
+
+    /// Writes TODOs in TodoR with the template set by `TodoRBuilder::set_template()`.
+    /// Every rendered part is followed by a newline and files without TODOs are skipped.
+    fn write_template(&self, out_buffer: &mut impl Write) -> Result<(), Error> {
+        let template = self.writer_config.template.as_ref().ok_or_else(|| {
+            TodoRError::InvalidOutputFormat {
+                message: "the template format needs a template".to_string(),
+            }
+        })?;
+
+        if let Some(header) = template.render_header(self.num_todos()) {
+            writeln!(out_buffer, "{}", header)?;
+        }
+
+        for todo_file in self.iter_files().filter(|f| !f.is_empty()) {
+            let file = todo_file.filepath.display().to_string();
+            if let Some(file_header) = template.render_file_header(&file, todo_file.len()) {
+                writeln!(out_buffer, "{}", file_header)?;
+            }
+            for ptodo in todo_file.iter_in_root(self.root()) {
+                writeln!(out_buffer, "{}", template.render_todo(&ptodo))?;
+            }
+            if let Some(file_footer) = template.render_file_footer(&file, todo_file.len()) {
+                writeln!(out_buffer, "{}", file_footer)?;
+            }
+        }
+
+        if let Some(footer) = template.render_footer(self.num_todos()) {
+            writeln!(out_buffer, "{}", footer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes TODOs in TodoR as a SARIF 2.1.0 log for code scanning tools.
+    /// Each tag is a rule and each TODO is a result with the level set for its tag.
+    fn write_sarif(&self, out_buffer: &mut impl Write) -> Result<(), Error> {
+        let mut tags: Vec<&str> = self.iter().map(|ptodo| ptodo.todo.tag.as_str()).collect();
+        tags.sort_unstable();
+        tags.dedup();
+
+        let rules: Vec<serde_json::Value> = tags
+            .iter()
+            .map(|tag| {
+                json!({
+                    "id": tag,
+                    "shortDescription": { "text": format!("{} comment", tag) },
+                    "defaultConfiguration": { "level": self.tag_severity(tag).as_str() },
+                })
+            })
+            .collect();
+
+        let results: Vec<serde_json::Value> = self
+            .iter()
+            .map(|ptodo| {
+                let todo = ptodo.todo;
+                let text = if todo.content.is_empty() {
+                    &todo.tag
+                } else {
+                    &todo.content
+                };
+
+                json!({
+                    "ruleId": todo.tag,
+                    "ruleIndex": tags.binary_search(&todo.tag.as_str()).unwrap_or(0),
+                    "level": self.tag_severity(&todo.tag).as_str(),
+                    "message": { "text": text },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": path_uri(ptodo.file) },
+                            "region": { "startLine": todo.line, "endLine": todo.end_line },
+                        },
+                    }],
+                    "partialFingerprints": { "todorId/v1": ptodo.id() },
+                })
+            })
+            .collect();
+
+        let log = json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "todor",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                        "rules": rules,
+                    },
+                },
+                "results": results,
+            }],
+        });
+
+        serde_json::to_writer_pretty(&mut *out_buffer, &log)?;
+        writeln!(out_buffer)?;
+        Ok(())
+    }
+
+    /// Gets the path of file relative to the workspace root with `/` separators.
+    /// Without a root, file is used as it is.
+    fn root_slash_path(&self, file: &Path) -> String {
+        match self.root() {
+            Some(root) => root_relative_path(file, root),
+            None => slash_path(file),
+        }
+    }
+
+    /// Writes TODOs in TodoR as GitHub Actions workflow commands so they are shown as
+    /// annotations on the lines they are on.
+    fn write_github(&self, out_buffer: &mut impl Write) -> Result<(), Error> {
         for ptodo in self.iter() {
             let todo = ptodo.todo;
+            let command = match self.tag_severity(&todo.tag) {
+                Severity::Note => "notice",
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+
             writeln!(
                 out_buffer,
-                "{}, {}, {}, {}",
-                ptodo.file.display(),
+                "::{} file={},line={},endLine={},title={}::{}",
+                command,
+                escape_workflow_property(&self.root_slash_path(ptodo.file)),
                 todo.line,
-                todo.tag,
-                todo.content,
+                todo.end_line,
+                escape_workflow_property(&todo.tag),
+                escape_workflow_data(&todo.content),
             )?;
         }
 
         Ok(())
     }
 
+    /// Writes TODOs in TodoR as a GitLab Code Quality report.
+    /// The ID of each TODO is used as its fingerprint so it is tracked when it moves.
+    fn write_gitlab(&self, out_buffer: &mut impl Write) -> Result<(), Error> {
+        let issues: Vec<serde_json::Value> = self
+            .iter()
+            .map(|ptodo| {
+                let todo = ptodo.todo;
+                let severity = match self.tag_severity(&todo.tag) {
+                    Severity::Note => "info",
+                    Severity::Warning => "minor",
+                    Severity::Error => "major",
+                };
+
+                json!({
+                    "description": format!("{}: {}", todo.tag, todo.content),
+                    "check_name": todo.tag,
+                    "fingerprint": ptodo.id(),
+                    "severity": severity,
+                    "location": {
+                        "path": self.root_slash_path(ptodo.file),
+                        "lines": { "begin": todo.line, "end": todo.end_line },
+                    },
+                })
+            })
+            .collect();
+
+        serde_json::to_writer_pretty(&mut *out_buffer, &issues)?;
+        writeln!(out_buffer)?;
+        Ok(())
+    }
+
+    /// Writes TODOs in TodoR as a JUnit XML report with a testsuite for each file.
+    /// Each TODO is a testcase that fails if its tag is an error and is skipped otherwise.
+    fn write_junit(&self, out_buffer: &mut impl Write) -> Result<(), Error> {
+        let is_failure = |tag: &str| self.tag_severity(tag) == Severity::Error;
+        let todo_files: Vec<&TodoFile> = self.iter_files().filter(|f| !f.is_empty()).collect();
+        let num_failures = self.iter().filter(|p| is_failure(&p.todo.tag)).count();
+
+        writeln!(out_buffer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out_buffer,
+            r#"<testsuites name="todor" tests="{}" failures="{}" skipped="{}">"#,
+            self.num_todos(),
+            num_failures,
+            self.num_todos() - num_failures,
+        )?;
+
+        for todo_file in todo_files {
+            let file = escape_xml(&slash_path(&todo_file.filepath));
+            let file_failures = todo_file
+                .todos
+                .iter()
+                .filter(|todo| is_failure(&todo.tag))
+                .count();
+            writeln!(
+                out_buffer,
+                r#"  <testsuite name="{}" tests="{}" failures="{}" skipped="{}">"#,
+                file,
+                todo_file.len(),
+                file_failures,
+                todo_file.len() - file_failures,
+            )?;
+
+            for todo in &todo_file.todos {
+                let tag = escape_xml(&todo.tag);
+                let content = escape_xml(&todo.content);
+                writeln!(
+                    out_buffer,
+                    r#"    <testcase name="{}: {}" classname="{}" file="{}" line="{}">"#,
+                    tag, content, file, file, todo.line,
+                )?;
+                if is_failure(&todo.tag) {
+                    writeln!(
+                        out_buffer,
+                        r#"      <failure type="{}" message="{}">{}:{}: {} {}</failure>"#,
+                        tag, content, file, todo.line, tag, content,
+                    )?;
+                } else {
+                    writeln!(out_buffer, r#"      <skipped message="{}"/>"#, content)?;
+                }
+                writeln!(out_buffer, "    </testcase>")?;
+            }
+
+            writeln!(out_buffer, "  </testsuite>")?;
+        }
+
+        writeln!(out_buffer, "</testsuites>")?;
+        Ok(())
+    }
+
+    /// Writes TODOs in TodoR as a Checkstyle XML report.
+    /// The source of each error is `todor.` followed by the tag of the TODO.
+    fn write_checkstyle(&self, out_buffer: &mut impl Write) -> Result<(), Error> {
+        writeln!(out_buffer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(out_buffer, r#"<checkstyle version="4.3">"#)?;
+
+        for todo_file in self.iter_files().filter(|f| !f.is_empty()) {
+            writeln!(
+                out_buffer,
+                r#"  <file name="{}">"#,
+                escape_xml(&slash_path(&todo_file.filepath))
+            )?;
+
+            for todo in &todo_file.todos {
+                let severity = match self.tag_severity(&todo.tag) {
+                    Severity::Note => "info",
+                    Severity::Warning => "warning",
+                    Severity::Error => "error",
+                };
+                writeln!(
+                    out_buffer,
+                    r#"    <error line="{}" severity="{}" message="{}" source="todor.{}"/>"#,
+                    todo.line,
+                    severity,
+                    escape_xml(&todo.content),
+                    escape_xml(&todo.tag),
+                )?;
+            }
+
+            writeln!(out_buffer, "  </file>")?;
+        }
+
+        writeln!(out_buffer, "</checkstyle>")?;
+        Ok(())
+    }
+
     /// Writes TODOs to out_buffer with no styles.
     fn write_default_todos(&self, out_buffer: &mut impl Write) -> Result<(), Error> {
         let styles = TodoRStyles::no_style();
 
         for todo_file in &self.todo_files {
-            write_file_todos(out_buffer, &todo_file, &styles)?;
+            write_file_todos(
+                out_buffer,
+                &todo_file,
+                &styles,
+                self.writer_config.show_ids,
+                self.root(),
+            )?;
         }
 
         Ok(())
@@ -170,9 +704,54 @@ impl TodoR {
             ReportFormat::Markdown => TodoR::write_markdown,
             ReportFormat::UserMarkdown => TodoR::write_user_markdown,
             ReportFormat::Csv => TodoR::write_csv,
+            ReportFormat::Tsv => TodoR::write_tsv,
+            ReportFormat::Sarif => TodoR::write_sarif,
+            ReportFormat::GitHub => TodoR::write_github,
+            ReportFormat::GitLab => TodoR::write_gitlab,
+            ReportFormat::JUnit => TodoR::write_junit,
+            ReportFormat::Checkstyle => TodoR::write_checkstyle,
+            ReportFormat::Template => TodoR::write_template,
             ReportFormat::Default => TodoR::write_default_todos,
         };
 
         formatted_write(self, out_buffer)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xml_escapes() {
+        assert_eq!(
+            escape_xml(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &apos;Jerry&apos;&lt;/a&gt;"
+        );
+        assert_eq!(escape_xml("a\tb\nc"), "a&#9;b&#10;c");
+        assert_eq!(escape_xml("bell\u{7}\u{FFFF}"), "bell\u{FFFD}\u{FFFD}");
+        assert_eq!(escape_xml("ünïcödé ✓"), "ünïcödé ✓");
+    }
+
+    #[test]
+    fn csv_escapes() {
+        assert_eq!(escape_csv("plain text"), "plain text");
+        assert_eq!(escape_csv("a, b"), "\"a, b\"");
+        assert_eq!(escape_csv("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_csv("two\nlines"), "\"two\nlines\"");
+        assert_eq!(escape_tsv("a\tb\\c\nd"), "a\\tb\\\\c\\nd");
+        assert!(Column::from_name("bogus").is_err());
+        assert_eq!(
+            Column::from_name("author_email").unwrap(),
+            Column::AuthorEmail
+        );
+    }
+
+    #[test]
+    fn slash_paths() {
+        let path: std::path::PathBuf = ["src", "bin", "main.rs"].iter().collect();
+        assert_eq!(slash_path(&path), "src/bin/main.rs");
+        assert_eq!(slash_path(Path::new("../main.rs")), "../main.rs");
+        assert_eq!(percent_encode("a b%.rs"), "a%20b%25.rs");
+    }
+}
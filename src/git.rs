@@ -0,0 +1,624 @@
+// Module for getting information from git
+
+use failure::Error;
+use fnv::{FnvHashMap, FnvHashSet};
+use log::{debug, warn};
+use std::env::current_dir;
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::cache::normalize_path;
+use crate::errors::TodoRError;
+use crate::TodoRBuilder;
+
+/// Runs git with args from dir, or the current directory if dir is None, and returns its stdout.
+pub(crate) fn run_git<I, S>(dir: Option<&Path>, args: I) -> Result<Vec<u8>, Error>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.arg("-C").arg(dir);
+    }
+    // keep non-ASCII paths readable instead of escaping them
+    command.arg("-c").arg("core.quotepath=false").args(args);
+
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(TodoRError::GitCommandFailed {
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }
+        .into());
+    }
+
+    Ok(output.stdout)
+}
+
+/// An entry for a file in a git tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TreeEntry {
+    /// Path from the root of the repository
+    pub path: String,
+    pub object: String,
+}
+
+/// Lists every regular file in the tree of the git revision rev.
+fn list_tree(rev: &str) -> Result<Vec<TreeEntry>, Error> {
+    let output = run_git(None, ["ls-tree", "-r", "-z", "--full-tree", rev])?;
+    Ok(parse_ls_tree(&String::from_utf8_lossy(&output)))
+}
+
+/// Parses the output of `git ls-tree -r -z`.
+/// Symlinks and submodules are skipped.
+fn parse_ls_tree(ls_tree: &str) -> Vec<TreeEntry> {
+    ls_tree
+        .split('\0')
+        .filter_map(|entry| {
+            // entries are `<mode> <type> <object>\t<path>`
+            let (info, path) = entry.split_once('\t')?;
+            let mut info = info.split(' ');
+            let (mode, kind, object) = (info.next()?, info.next()?, info.next()?);
+            if kind != "blob" || mode == "120000" {
+                return None;
+            }
+
+            Some(TreeEntry {
+                path: path.to_string(),
+                object: object.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Lists every regular file in the git index with paths from the root of the repository.
+/// Files with merge conflicts are skipped since they have no single staged version.
+fn list_index() -> Result<Vec<TreeEntry>, Error> {
+    let output = run_git(None, ["ls-files", "-s", "-z", "--full-name", "--", ":/"])?;
+    Ok(parse_ls_files(&String::from_utf8_lossy(&output)))
+}
+
+/// Parses the output of `git ls-files -s -z`.
+/// Symlinks, submodules and unmerged entries are skipped.
+fn parse_ls_files(ls_files: &str) -> Vec<TreeEntry> {
+    ls_files
+        .split('\0')
+        .filter_map(|entry| {
+            // entries are `<mode> <object> <stage>\t<path>`
+            let (info, path) = entry.split_once('\t')?;
+            let mut info = info.split(' ');
+            let (mode, object, stage) = (info.next()?, info.next()?, info.next()?);
+            if mode == "120000" || mode == "160000" || stage != "0" {
+                return None;
+            }
+
+            Some(TreeEntry {
+                path: path.to_string(),
+                object: object.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Reads the content of each git object in objects using a single `git cat-file` process.
+/// f is called with the index and content of each object before the next one is read,
+/// so only one object is held in memory at a time.
+fn read_blobs_with<F>(objects: &[&str], mut f: F) -> Result<(), Error>
+where
+    F: FnMut(usize, Vec<u8>) -> Result<(), Error>,
+{
+    let mut child = Command::new("git")
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    // write from another thread so a full stdout pipe cannot block the requests
+    let mut stdin = child.stdin.take().unwrap();
+    let requests: String = objects.iter().map(|o| format!("{}\n", o)).collect();
+    let writer = thread::spawn(move || stdin.write_all(requests.as_bytes()));
+
+    let mut reader = BufReader::new(child.stdout.take().unwrap());
+    let result = objects
+        .iter()
+        .enumerate()
+        .try_for_each(|(i, object)| f(i, read_blob(&mut reader, object)?));
+
+    if result.is_err() {
+        // stop git so the writer is not left blocked on a pipe nobody reads
+        let _ = child.kill();
+    }
+    drop(reader);
+    let written = writer.join().unwrap();
+    child.wait()?;
+    result?;
+    written?;
+    Ok(())
+}
+
+/// Reads the next object from the output of `git cat-file --batch`.
+fn read_blob(reader: &mut impl BufRead, object: &str) -> Result<Vec<u8>, Error> {
+    let mut header = String::new();
+    reader.read_line(&mut header)?;
+
+    // header is `<object> <type> <size>` or `<object> missing`
+    let size: usize = header
+        .trim_end()
+        .rsplit(' ')
+        .next()
+        .and_then(|size| size.parse().ok())
+        .ok_or_else(|| TodoRError::GitCommandFailed {
+            message: format!("cannot read object {}", object),
+        })?;
+
+    // content is followed by a newline
+    let mut blob = vec![0; size + 1];
+    reader.read_exact(&mut blob)?;
+    blob.truncate(size);
+    Ok(blob)
+}
+
+/// Reads the content of the file at path in entries if there is one.
+fn read_entry(entries: &[TreeEntry], path: &str) -> Result<Option<Vec<u8>>, Error> {
+    let entry = match entries.iter().find(|e| e.path == path) {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+
+    let mut content = None;
+    read_blobs_with(&[entry.object.as_str()], |_, blob| {
+        content = Some(blob);
+        Ok(())
+    })?;
+    Ok(content)
+}
+
+/// Files in a git tree or the index read straight from git without checking them out.
+#[derive(Debug, Clone)]
+pub struct GitTree {
+    source: String,
+    /// Current directory relative to the root of the repository, like `src/`
+    prefix: String,
+    /// Files with paths from the root of the repository
+    entries: Vec<TreeEntry>,
+    /// Content of the `.todor` and `.todorignore` files in the root
+    config: Option<Vec<u8>>,
+    ignore: Option<Vec<u8>>,
+}
+
+impl GitTree {
+    /// Lists every regular file in the tree of the git revision rev.
+    pub fn at_rev(rev: &str) -> Result<GitTree, Error> {
+        GitTree::read(rev, list_tree(rev)?)
+    }
+
+    /// Lists every file staged in the git index.
+    /// This is the content that the next commit will have.
+    pub fn staged() -> Result<GitTree, Error> {
+        GitTree::read("the index", list_index()?)
+    }
+
+    /// Reads the config files in entries. source names where they are from in messages.
+    /// The content of the other files is only read when they are searched.
+    fn read(source: &str, entries: Vec<TreeEntry>) -> Result<GitTree, Error> {
+        let config = read_entry(&entries, ".todor")?;
+        let ignore = read_entry(&entries, ".todorignore")?;
+        let prefix = run_git(None, ["rev-parse", "--show-prefix"])?;
+
+        Ok(GitTree {
+            source: source.to_string(),
+            prefix: String::from_utf8_lossy(&prefix).trim_end().to_string(),
+            entries,
+            config,
+            ignore,
+        })
+    }
+
+    /// Adds the `.todor` config in the root of the tree to builder.
+    /// An invalid config is skipped with a warning.
+    pub fn add_config_to(&self, builder: &mut TodoRBuilder) {
+        if let Some(blob) = &self.config {
+            // check for empty file before adding
+            if blob.len() > 2 {
+                let content = String::from_utf8_lossy(blob);
+                if let Err(err) = builder.add_config_str(&content, config::FileFormat::Hjson) {
+                    warn!("ignoring invalid .todor in {}: {}", self.source, err);
+                }
+            }
+        }
+    }
+
+    /// Reads the files that should be searched one at a time and calls f with the path
+    /// and content of each. Files that are not valid UTF-8 are skipped with a warning.
+    ///
+    /// Files matched by the `.todorignore` in the root of the tree or by ignore_lines,
+    /// which use the same syntax, are skipped.
+    pub fn for_each_searched_file<F>(&self, ignore_lines: &[&str], mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(&str, &str) -> Result<(), Error>,
+    {
+        let mut ignore_builder = ignore::gitignore::GitignoreBuilder::new("");
+        if let Some(blob) = &self.ignore {
+            for line in String::from_utf8_lossy(blob).lines() {
+                ignore_builder.add_line(None, line)?;
+            }
+        }
+        for line in ignore_lines {
+            ignore_builder.add_line(None, line)?;
+        }
+        let ignores = ignore_builder.build()?;
+
+        let searched: Vec<&TreeEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| {
+                let ignored = ignores
+                    .matched_path_or_any_parents(Path::new(&entry.path), false)
+                    .is_ignore();
+                if ignored {
+                    debug!("ignoring `{}`", entry.path);
+                }
+                !ignored
+            })
+            .collect();
+        let objects: Vec<&str> = searched.iter().map(|e| e.object.as_str()).collect();
+
+        read_blobs_with(&objects, |i, blob| {
+            let path = &searched[i].path;
+            match String::from_utf8(blob) {
+                Ok(content) => f(path, &content),
+                Err(_) => {
+                    warn!("skipping `{}` in {}: not valid UTF-8", path, self.source);
+                    Ok(())
+                }
+            }
+        })
+    }
+
+    /// Turns a path from the root of the repository into one relative to the current directory
+    /// like the paths found when walking the workspace.
+    pub fn relative_path(&self, path: &str) -> PathBuf {
+        relative_to_prefix(path, &self.prefix)
+    }
+}
+
+/// Makes path relative to the directory prefix when both are relative to the same root.
+fn relative_to_prefix(path: &str, prefix: &str) -> PathBuf {
+    let prefix_parts: Vec<&str> = prefix.split('/').filter(|p| !p.is_empty()).collect();
+    let path_parts: Vec<&str> = path.split('/').collect();
+    let common = prefix_parts
+        .iter()
+        .zip(&path_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..prefix_parts.len() {
+        relative.push("..");
+    }
+    for part in &path_parts[common..] {
+        relative.push(part);
+    }
+    relative
+}
+
+/// Finds the directory git runs hooks from for the current repository.
+/// Relative paths are relative to the current directory.
+pub fn hooks_dir() -> Result<PathBuf, Error> {
+    let output = run_git(None, ["rev-parse", "--git-path", "hooks"])?;
+    Ok(PathBuf::from(String::from_utf8_lossy(&output).trim_end()))
+}
+
+/// Files tracked by git in the repository of the current directory.
+#[derive(Debug, Clone, Default)]
+pub struct TrackedFiles {
+    curr_dir: PathBuf,
+    // Absolute paths of tracked files and of the directories that hold them
+    files: FnvHashSet<PathBuf>,
+    dirs: FnvHashSet<PathBuf>,
+}
+
+impl TrackedFiles {
+    /// Lists the files in the git index.
+    /// Submodules are listed as a single entry unless recurse_submodules is true,
+    /// in which case the files tracked in each submodule are listed instead.
+    pub fn list(recurse_submodules: bool) -> Result<TrackedFiles, Error> {
+        let curr_dir = current_dir()?.canonicalize()?;
+        let toplevel = run_git(None, ["rev-parse", "--show-toplevel"])?;
+        let toplevel = PathBuf::from(String::from_utf8_lossy(&toplevel).trim());
+
+        let mut args = vec!["ls-files", "-z", "--full-name"];
+        if recurse_submodules {
+            args.push("--recurse-submodules");
+        }
+        args.extend(&["--", ":/"]);
+        let output = run_git(None, &args)?;
+
+        let mut tracked_files = TrackedFiles {
+            curr_dir,
+            ..TrackedFiles::default()
+        };
+        for path in String::from_utf8_lossy(&output).split('\0') {
+            if path.is_empty() {
+                continue;
+            }
+
+            let path = toplevel.join(path);
+            for dir in path.ancestors().skip(1) {
+                // the rest of the ancestors were added with an earlier file
+                if !tracked_files.dirs.insert(dir.to_path_buf()) {
+                    break;
+                }
+            }
+            tracked_files.files.insert(path);
+        }
+
+        Ok(tracked_files)
+    }
+
+    /// Returns true if the file at filepath is tracked.
+    /// Relative filepaths are taken from the current directory when the files were listed.
+    pub fn contains_file(&self, filepath: &Path) -> bool {
+        self.files
+            .contains(&normalize_path(&self.curr_dir.join(filepath)))
+    }
+
+    /// Returns true if the directory at dirpath holds any tracked files.
+    /// Relative dirpaths are taken from the current directory when the files were listed.
+    pub fn contains_dir(&self, dirpath: &Path) -> bool {
+        self.dirs
+            .contains(&normalize_path(&self.curr_dir.join(dirpath)))
+    }
+}
+
+/// Lines that were added or changed in each file since a git revision.
+#[derive(Debug, Clone, Default)]
+pub struct ChangedLines {
+    curr_dir: PathBuf,
+    // Inclusive line ranges keyed by absolute file path
+    files: FnvHashMap<PathBuf, Vec<(usize, usize)>>,
+}
+
+impl ChangedLines {
+    /// Finds the lines in the working tree that changed since the git revision rev.
+    /// Files that git does not track yet count as entirely changed.
+    ///
+    /// If staged is true, the index is compared with rev instead of the working tree.
+    pub fn since(rev: &str, staged: bool) -> Result<ChangedLines, Error> {
+        let curr_dir = current_dir()?.canonicalize()?;
+        let toplevel = run_git(None, ["rev-parse", "--show-toplevel"])?;
+        let toplevel = PathBuf::from(String::from_utf8_lossy(&toplevel).trim());
+
+        // prefixes are set so diff.noprefix and diff.mnemonicPrefix do not change the headers
+        let mut args = vec![
+            "diff",
+            "--no-color",
+            "--no-ext-diff",
+            "--unified=0",
+            "--src-prefix=a/",
+            "--dst-prefix=b/",
+        ];
+        if staged {
+            args.push("--cached");
+        }
+        args.extend(&[rev, "--"]);
+        let diff = run_git(None, &args)?;
+
+        let mut changed_lines = ChangedLines {
+            curr_dir,
+            files: parse_diff(&String::from_utf8_lossy(&diff), &toplevel),
+        };
+
+        if !staged {
+            let untracked = run_git(
+                None,
+                [
+                    "ls-files",
+                    "--others",
+                    "--exclude-standard",
+                    "-z",
+                    "--full-name",
+                    "--",
+                    ":/",
+                ],
+            )?;
+            let untracked = String::from_utf8_lossy(&untracked);
+            for path in untracked.split('\0').filter(|path| !path.is_empty()) {
+                changed_lines
+                    .files
+                    .insert(toplevel.join(path), vec![(1, usize::MAX)]);
+            }
+        }
+
+        Ok(changed_lines)
+    }
+
+    /// Returns true if any line from first to last in filepath changed.
+    /// Relative filepaths are taken from the current directory when the changes were found.
+    pub fn contains(&self, filepath: &Path, first: usize, last: usize) -> bool {
+        let key = normalize_path(&self.curr_dir.join(filepath));
+        match self.files.get(&key) {
+            Some(ranges) => ranges
+                .iter()
+                .any(|&(start, end)| start <= last && first <= end),
+            None => false,
+        }
+    }
+}
+
+/// Parses the output of `git diff --unified=0` into the ranges of lines added in each file.
+/// File paths in the diff are relative to toplevel.
+fn parse_diff(diff: &str, toplevel: &Path) -> FnvHashMap<PathBuf, Vec<(usize, usize)>> {
+    let mut files: FnvHashMap<PathBuf, Vec<(usize, usize)>> = FnvHashMap::default();
+    let mut current_file: Option<PathBuf> = None;
+    // added lines can look like `+++` headers so those are only read before the first hunk
+    let mut in_header = false;
+
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            in_header = true;
+            current_file = None;
+        } else if let Some(hunk) = line.strip_prefix("@@ ") {
+            in_header = false;
+            if let (Some(file), Some(range)) = (&current_file, parse_hunk_header(hunk)) {
+                files.entry(file.clone()).or_default().push(range);
+            }
+        } else if in_header {
+            if let Some(new_path) = line.strip_prefix("+++ ") {
+                // deleted files have no new lines
+                current_file = unquote_path(new_path)
+                    .strip_prefix("b/")
+                    .map(|path| toplevel.join(path));
+            }
+        }
+    }
+
+    files
+}
+
+/// Removes the quotes git puts around paths with special characters.
+fn unquote_path(path: &str) -> &str {
+    path.strip_prefix('"')
+        .and_then(|p| p.strip_suffix('"'))
+        .unwrap_or(path)
+}
+
+/// Parses the new side of a hunk header like `-3,2 +4,5 @@` into an inclusive line range.
+/// Returns None for hunks that only remove lines.
+fn parse_hunk_header(hunk: &str) -> Option<(usize, usize)> {
+    let new_side = hunk.split(' ').find_map(|part| part.strip_prefix('+'))?;
+    let mut parts = new_side.splitn(2, ',');
+    let start: usize = parts.next()?.parse().ok()?;
+    let count: usize = match parts.next() {
+        Some(count) => count.parse().ok()?,
+        None => 1,
+    };
+
+    if count == 0 {
+        None
+    } else {
+        Some((start, start + count - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hunk_headers() {
+        assert_eq!(parse_hunk_header("-3,2 +4,5 @@ fn main() {"), Some((4, 8)));
+        assert_eq!(parse_hunk_header("-3 +4 @@"), Some((4, 4)));
+        assert_eq!(parse_hunk_header("-3,2 +2,0 @@"), None);
+    }
+
+    #[test]
+    fn diff() {
+        let diff = "\
+diff --git a/src/main.rs b/src/main.rs
+index 1234567..89abcde 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,0 +2,2 @@ fn main() {
++    // TODO: new
++    // TODO: newer
+@@ -10 +11,0 @@
+-    // TODO: old
+diff --git a/old.rs b/old.rs
+deleted file mode 100644
+--- a/old.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-// TODO: gone
+diff --git a/new.py b/new.py
+new file mode 100644
+--- /dev/null
++++ b/new.py
+@@ -0,0 +1 @@
++# TODO: added
+@@ -3,0 +5 @@
++++ b/looks_like_header
+";
+        let files = parse_diff(diff, Path::new("/repo"));
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[Path::new("/repo/src/main.rs")], vec![(2, 3)]);
+        assert_eq!(files[Path::new("/repo/new.py")], vec![(1, 1), (5, 5)]);
+    }
+
+    #[test]
+    fn ls_tree() {
+        let ls_tree = "100644 blob 1a2b3c\tsrc/main.rs\0\
+                       120000 blob 4d5e6f\tlink.rs\0\
+                       160000 commit 7a8b9c\tsubmodule\0\
+                       100755 blob 0d1e2f\tsrc/with space.sh\0";
+
+        assert_eq!(
+            parse_ls_tree(ls_tree),
+            vec![
+                TreeEntry {
+                    path: "src/main.rs".to_string(),
+                    object: "1a2b3c".to_string(),
+                },
+                TreeEntry {
+                    path: "src/with space.sh".to_string(),
+                    object: "0d1e2f".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn ls_files() {
+        let ls_files = "100644 1a2b3c 0\tsrc/main.rs\0\
+                        120000 4d5e6f 0\tlink.rs\0\
+                        160000 7a8b9c 0\tsubmodule\0\
+                        100644 0d1e2f 2\tconflict.rs\0\
+                        100644 3a4b5c 3\tconflict.rs\0";
+
+        assert_eq!(
+            parse_ls_files(ls_files),
+            vec![TreeEntry {
+                path: "src/main.rs".to_string(),
+                object: "1a2b3c".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn prefix_paths() {
+        assert_eq!(
+            relative_to_prefix("src/main.rs", ""),
+            Path::new("src/main.rs")
+        );
+        assert_eq!(
+            relative_to_prefix("src/main.rs", "src/"),
+            Path::new("main.rs")
+        );
+        assert_eq!(
+            relative_to_prefix("README.md", "src/bin/"),
+            Path::new("../../README.md")
+        );
+        assert_eq!(
+            relative_to_prefix("src/lib.rs", "src/bin/"),
+            Path::new("../lib.rs")
+        );
+    }
+
+    #[test]
+    fn contains() {
+        let mut files = FnvHashMap::default();
+        files.insert(PathBuf::from("/repo/src/main.rs"), vec![(2, 3), (10, 10)]);
+        let changed_lines = ChangedLines {
+            curr_dir: PathBuf::from("/repo/src"),
+            files,
+        };
+
+        assert!(changed_lines.contains(Path::new("main.rs"), 3, 3));
+        assert!(changed_lines.contains(Path::new("../src/main.rs"), 8, 12));
+        assert!(!changed_lines.contains(Path::new("main.rs"), 4, 9));
+        assert!(!changed_lines.contains(Path::new("other.rs"), 2, 2));
+    }
+}
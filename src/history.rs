@@ -0,0 +1,276 @@
+// Module for counting TODOs over the git history of a workspace
+
+use failure::Error;
+use log::info;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::blame::civil_from_days;
+use crate::errors::TodoRError;
+use crate::git::{run_git, GitTree};
+use crate::TodoRBuilder;
+
+/// How far apart sampled commits are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryStep {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl HistoryStep {
+    /// Parses a step from `daily`, `weekly` or `monthly`.
+    pub fn from_name(name: &str) -> Result<HistoryStep, Error> {
+        match name {
+            "daily" => Ok(HistoryStep::Daily),
+            "weekly" => Ok(HistoryStep::Weekly),
+            "monthly" => Ok(HistoryStep::Monthly),
+            _ => Err(TodoRError::InvalidHistoryStep {
+                step: name.to_string(),
+            }
+            .into()),
+        }
+    }
+
+    /// Returns the number of the step that the unix time falls in.
+    fn period(self, time: i64) -> i64 {
+        let days = time.div_euclid(86400);
+        match self {
+            HistoryStep::Daily => days,
+            // weeks start on Monday and the unix epoch was a Thursday
+            HistoryStep::Weekly => (days + 3).div_euclid(7),
+            HistoryStep::Monthly => {
+                let (year, month, _) = civil_from_days(days);
+                year * 12 + month
+            }
+        }
+    }
+}
+
+/// A commit picked from the history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryCommit {
+    pub hash: String,
+    /// Committer date in ISO 8601 format
+    pub date: String,
+}
+
+/// Picks the last commit of every step in the history of the current branch.
+/// Only commits after since are used if it is given. since can be any date git understands
+/// such as `6.months` or `2019-01-01`.
+///
+/// Commits are returned from oldest to newest.
+pub fn sample_commits(since: Option<&str>, step: HistoryStep) -> Result<Vec<HistoryCommit>, Error> {
+    let mut args = vec![
+        "log".to_string(),
+        "--first-parent".to_string(),
+        "--format=%H %ct %cI".to_string(),
+    ];
+    if let Some(since) = since {
+        args.push(format!("--since={}", since));
+    }
+    let log = run_git(None, &args)?;
+
+    Ok(sample_log(&String::from_utf8_lossy(&log), step))
+}
+
+/// Picks the last commit of every step from `git log` output that lists the newest commit first.
+fn sample_log(log: &str, step: HistoryStep) -> Vec<HistoryCommit> {
+    let mut samples: Vec<(i64, HistoryCommit)> = Vec::new();
+    for line in log.lines().rev() {
+        let mut parts = line.split(' ');
+        let (hash, time, date) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(hash), Some(time), Some(date)) => (hash, time, date),
+            _ => continue,
+        };
+        let period = match time.parse() {
+            Ok(time) => step.period(time),
+            Err(_) => continue,
+        };
+
+        let commit = HistoryCommit {
+            hash: hash.to_string(),
+            date: date.to_string(),
+        };
+        match samples.last_mut() {
+            // later commits in the same step replace earlier ones
+            Some((last_period, last_commit)) if *last_period == period => *last_commit = commit,
+            _ => samples.push((period, commit)),
+        }
+    }
+
+    samples.into_iter().map(|(_, commit)| commit).collect()
+}
+
+/// Number of TODOs found in a commit by tag and by user.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TodoCounts {
+    pub date: String,
+    pub commit: String,
+    pub total: usize,
+    pub tags: BTreeMap<String, usize>,
+    pub users: BTreeMap<String, usize>,
+}
+
+/// Counts the TODOs in every file of the commit without checking it out.
+///
+/// builder is extended with the `.todor` config in the root of the repository at the commit.
+/// Files matched by the `.todorignore` in the root at the commit or by ignore_lines,
+/// which use the same syntax, are skipped.
+pub fn count_todos_at(
+    commit: &HistoryCommit,
+    builder: &TodoRBuilder,
+    ignore_lines: &[&str],
+) -> Result<TodoCounts, Error> {
+    info!("counting TODOs at commit {}...", commit.hash);
+    let tree = GitTree::at_rev(&commit.hash)?;
+    let mut builder = builder.clone();
+    tree.add_config_to(&mut builder);
+    let parser = builder.build_parser()?;
+
+    let mut counts = TodoCounts {
+        date: commit.date.clone(),
+        commit: commit.hash.clone(),
+        ..TodoCounts::default()
+    };
+    tree.for_each_searched_file(ignore_lines, |path, content| {
+        let todo_file = parser.parse_content_at(content, path)?;
+        for todo in &todo_file.todos {
+            counts.total += 1;
+            *counts.tags.entry(todo.tag.clone()).or_insert(0) += 1;
+
+            let mut users = todo.users();
+            users.sort_unstable();
+            users.dedup();
+            for user in users {
+                *counts.users.entry(user.to_string()).or_insert(0) += 1;
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(counts)
+}
+
+/// Writes the counts in a csv format with a column for every tag and user seen in any commit.
+pub fn write_history_csv(history: &[TodoCounts], out_buffer: &mut impl Write) -> Result<(), Error> {
+    let mut tags: Vec<&String> = history.iter().flat_map(|c| c.tags.keys()).collect();
+    tags.sort();
+    tags.dedup();
+    let mut users: Vec<&String> = history.iter().flat_map(|c| c.users.keys()).collect();
+    users.sort();
+    users.dedup();
+
+    write!(out_buffer, "date, commit, total")?;
+    for column in tags.iter().chain(&users) {
+        write!(out_buffer, ", {}", column)?;
+    }
+    writeln!(out_buffer)?;
+
+    for counts in history {
+        write!(
+            out_buffer,
+            "{}, {}, {}",
+            counts.date, counts.commit, counts.total
+        )?;
+        for tag in &tags {
+            write!(out_buffer, ", {}", counts.tags.get(*tag).unwrap_or(&0))?;
+        }
+        for user in &users {
+            write!(out_buffer, ", {}", counts.users.get(*user).unwrap_or(&0))?;
+        }
+        writeln!(out_buffer)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the counts serialized in the JSON format.
+pub fn write_history_json(
+    history: &[TodoCounts],
+    out_buffer: &mut impl Write,
+) -> Result<(), Error> {
+    serde_json::to_writer_pretty(out_buffer, history)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn periods() {
+        // 2019-01-11 was a Friday
+        let friday = 1_547_164_800;
+        let monday = friday + 3 * 86400;
+        let sunday = friday + 2 * 86400;
+
+        assert_eq!(
+            HistoryStep::Daily.period(friday) + 1,
+            HistoryStep::Daily.period(friday + 86400)
+        );
+        assert_eq!(
+            HistoryStep::Weekly.period(friday),
+            HistoryStep::Weekly.period(sunday)
+        );
+        assert_ne!(
+            HistoryStep::Weekly.period(sunday),
+            HistoryStep::Weekly.period(monday)
+        );
+        // 2019-01-31 and 2019-02-01
+        assert_ne!(
+            HistoryStep::Monthly.period(1_548_892_800),
+            HistoryStep::Monthly.period(1_548_979_200)
+        );
+        assert!(HistoryStep::from_name("yearly").is_err());
+    }
+
+    #[test]
+    fn sample() {
+        let log = "\
+ccc 1548979200 2019-02-01T00:00:00+00:00
+bbb 1548892800 2019-01-31T00:00:00+00:00
+aaa 1547164800 2019-01-11T00:00:00+00:00
+";
+        let hashes = |step| -> Vec<String> {
+            sample_log(log, step)
+                .into_iter()
+                .map(|commit| commit.hash)
+                .collect()
+        };
+
+        assert_eq!(hashes(HistoryStep::Monthly), vec!["bbb", "ccc"]);
+        assert_eq!(hashes(HistoryStep::Weekly), vec!["aaa", "ccc"]);
+        assert_eq!(hashes(HistoryStep::Daily), vec!["aaa", "bbb", "ccc"]);
+    }
+
+    #[test]
+    fn csv() {
+        let mut first = TodoCounts {
+            date: "2019-01-11".to_string(),
+            commit: "aaa".to_string(),
+            total: 1,
+            ..TodoCounts::default()
+        };
+        first.tags.insert("TODO".to_string(), 1);
+        let mut second = TodoCounts {
+            date: "2019-01-18".to_string(),
+            commit: "bbb".to_string(),
+            total: 2,
+            ..TodoCounts::default()
+        };
+        second.tags.insert("FIXME".to_string(), 2);
+        second.users.insert("@ann".to_string(), 1);
+
+        let mut out: Vec<u8> = Vec::new();
+        write_history_csv(&[first, second], &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "date, commit, total, FIXME, TODO, @ann
+2019-01-11, aaa, 1, 0, 1, 0
+2019-01-18, bbb, 2, 2, 0, 1
+"
+        );
+    }
+}
@@ -0,0 +1,526 @@
+// Module for lexing code to find where comments and string literals are
+
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
+
+use crate::comments::{CharLiteral, CommentTypes, QuotedString, RawString, StringType};
+
+/// State of the lexer at the start or end of a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LexState {
+    Code,
+    /// Inside of the block comment type with the given index.
+    BlockComment(usize),
+    /// Inside of the multi-line quoted string type with the given index.
+    String(usize),
+    /// Inside of a raw string that is closed by `"` followed by the given number of `#`s.
+    RawString(usize),
+}
+
+/// The kind of content held by a span of a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanKind {
+    Code,
+    String,
+    /// Comment of the comment type with the given index.
+    Comment(usize),
+}
+
+/// A span of bytes in a line that holds one kind of content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub kind: SpanKind,
+    /// True if the span started on a previous line.
+    pub continued: bool,
+}
+
+/// Comment prefix and suffix tokens.
+#[derive(Debug, Clone)]
+struct CommentToken {
+    prefix: String,
+    suffix: Option<String>,
+}
+
+/// End of a string literal that starts in a line.
+enum StringEnd {
+    /// String ends at the given position.
+    Closed(usize),
+    /// String is still open at the end of the line.
+    Open(LexState),
+}
+
+/// Token that starts a comment or a string literal.
+#[derive(Debug, Clone, Copy)]
+enum Token {
+    Comment(usize),
+    String(usize),
+}
+
+/// Lightweight lexer that splits lines into code, string literals, and comments.
+///
+/// If no string literal types are provided, comment prefixes are never skipped.
+#[derive(Debug, Clone)]
+pub struct Lexer {
+    comments: Vec<CommentToken>,
+    strings: Vec<StringType>,
+    /// Searches for all the tokens that start comments and strings in a single pass.
+    token_finder: AhoCorasick,
+    /// Token for each pattern in token_finder.
+    tokens: Vec<Token>,
+}
+
+impl Lexer {
+    /// Creates new Lexer from the comment and string literal types in `comment_types`.
+    pub fn new(comment_types: &CommentTypes) -> Lexer {
+        let comments = comment_types
+            .iter()
+            .map(|c| CommentToken {
+                prefix: c.prefix().to_string(),
+                suffix: c.suffix().map(|s| s.to_string()),
+            })
+            .collect();
+
+        // Patterns are ordered so that comment tokens are preferred over string tokens,
+        // and longer tokens are preferred over shorter ones when they start at the same position.
+        let mut patterns: Vec<(&str, Token)> = comment_types
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c.prefix(), Token::Comment(i)))
+            .chain(
+                comment_types
+                    .strings()
+                    .enumerate()
+                    .map(|(j, s)| (s.prefix(), Token::String(j))),
+            )
+            .filter(|(prefix, _)| !prefix.is_empty())
+            .collect();
+        patterns.sort_by_key(|(prefix, token)| {
+            let priority = match token {
+                Token::Comment(_) => 0,
+                Token::String(_) => 1,
+            };
+            (priority, std::cmp::Reverse(prefix.len()))
+        });
+
+        let token_finder = AhoCorasickBuilder::new()
+            .match_kind(MatchKind::LeftmostFirst)
+            .build(patterns.iter().map(|(prefix, _)| prefix))
+            .unwrap();
+
+        Lexer {
+            comments,
+            strings: comment_types.strings().cloned().collect(),
+            token_finder,
+            tokens: patterns.into_iter().map(|(_, token)| token).collect(),
+        }
+    }
+
+    /// Returns the suffix of the comment type with the given index if it is a block comment.
+    pub fn suffix(&self, comment_index: usize) -> Option<&str> {
+        self.comments[comment_index].suffix.as_deref()
+    }
+
+    /// Lexes a line that starts in `state`.
+    ///
+    /// Returns the spans that make up the line along with the state at the end of the line.
+    pub fn lex_line(&self, line: &str, state: LexState) -> (Vec<Span>, LexState) {
+        let mut spans = Vec::new();
+        let mut state = state;
+        let mut pos = 0;
+        let mut span_start = 0;
+        let mut continued = state != LexState::Code;
+
+        loop {
+            match state {
+                LexState::Code => {
+                    let (start, token) = match self.next_token(line, pos) {
+                        Some(next_token) => next_token,
+                        None => {
+                            push_code_span(&mut spans, span_start, line.len());
+                            return (spans, state);
+                        }
+                    };
+
+                    match token {
+                        Token::Comment(i) => {
+                            push_code_span(&mut spans, span_start, start);
+                            let comment = &self.comments[i];
+                            if comment.suffix.is_none() {
+                                // single-line comments run until the end of the line
+                                spans.push(Span {
+                                    start,
+                                    end: line.len(),
+                                    kind: SpanKind::Comment(i),
+                                    continued: false,
+                                });
+                                return (spans, state);
+                            }
+
+                            state = LexState::BlockComment(i);
+                            span_start = start;
+                            pos = start + comment.prefix.len();
+                            continued = false;
+                        }
+                        Token::String(j) => {
+                            let string_type = &self.strings[j];
+                            let string_end = match string_type {
+                                StringType::Quoted(q) => lex_quoted(line, start, j, q),
+                                StringType::Char(c) => lex_char(line, start, c),
+                                StringType::Raw(r) => lex_raw(line, start, r),
+                            };
+
+                            match string_end {
+                                // token is not the start of a string
+                                None => pos = start + string_type.prefix().len(),
+                                Some(StringEnd::Closed(end)) => {
+                                    push_code_span(&mut spans, span_start, start);
+                                    spans.push(Span {
+                                        start,
+                                        end,
+                                        kind: SpanKind::String,
+                                        continued: false,
+                                    });
+                                    span_start = end;
+                                    pos = end;
+                                }
+                                Some(StringEnd::Open(string_state)) => {
+                                    push_code_span(&mut spans, span_start, start);
+                                    spans.push(Span {
+                                        start,
+                                        end: line.len(),
+                                        kind: SpanKind::String,
+                                        continued: false,
+                                    });
+                                    return (spans, string_state);
+                                }
+                            }
+                        }
+                    }
+                }
+                LexState::BlockComment(i) => {
+                    let suffix = self.comments[i].suffix.as_ref().unwrap();
+                    let end = match line[pos..].find(suffix.as_str()) {
+                        Some(end) => pos + end + suffix.len(),
+                        None => {
+                            spans.push(Span {
+                                start: span_start,
+                                end: line.len(),
+                                kind: SpanKind::Comment(i),
+                                continued,
+                            });
+                            return (spans, state);
+                        }
+                    };
+
+                    spans.push(Span {
+                        start: span_start,
+                        end,
+                        kind: SpanKind::Comment(i),
+                        continued,
+                    });
+
+                    state = LexState::Code;
+                    span_start = end;
+                    pos = end;
+                    continued = false;
+                }
+                LexState::String(_) | LexState::RawString(_) => {
+                    let string_end = match state {
+                        LexState::String(j) => match &self.strings[j] {
+                            StringType::Quoted(q) => find_quoted_end(line, pos, q),
+                            _ => unreachable!(),
+                        },
+                        LexState::RawString(hashes) => find_raw_end(line, pos, hashes),
+                        _ => unreachable!(),
+                    };
+
+                    spans.push(Span {
+                        start: span_start,
+                        end: string_end.unwrap_or(line.len()),
+                        kind: SpanKind::String,
+                        continued,
+                    });
+
+                    match string_end {
+                        Some(end) => {
+                            state = LexState::Code;
+                            span_start = end;
+                            pos = end;
+                            continued = false;
+                        }
+                        None => return (spans, state),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Finds the token that occurs first in code starting from `pos`.
+    fn next_token(&self, line: &str, pos: usize) -> Option<(usize, Token)> {
+        self.token_finder
+            .find(&line[pos..])
+            .map(|m| (pos + m.start(), self.tokens[m.pattern().as_usize()]))
+    }
+}
+
+/// Adds a code span to spans if it is not empty.
+fn push_code_span(spans: &mut Vec<Span>, start: usize, end: usize) {
+    if start < end {
+        spans.push(Span {
+            start,
+            end,
+            kind: SpanKind::Code,
+            continued: false,
+        });
+    }
+}
+
+/// Lexes a quoted string that starts at `start`.
+/// Returns `None` if the delimiter does not start a string.
+fn lex_quoted(line: &str, start: usize, index: usize, quoted: &QuotedString) -> Option<StringEnd> {
+    let body_start = start + quoted.delimiter.len();
+    match find_quoted_end(line, body_start, quoted) {
+        Some(end) => Some(StringEnd::Closed(end)),
+        None if quoted.multiline => Some(StringEnd::Open(LexState::String(index))),
+        // strings that cannot span lines are treated as code when not closed
+        None => None,
+    }
+}
+
+/// Returns the position right after the delimiter that ends a quoted string.
+fn find_quoted_end(line: &str, pos: usize, quoted: &QuotedString) -> Option<usize> {
+    let bytes = line.as_bytes();
+    let delimiter = quoted.delimiter.as_bytes();
+    let mut i = pos;
+    while i < bytes.len() {
+        if quoted.escape && bytes[i] == b'\\' {
+            i += 2;
+        } else if bytes[i..].starts_with(delimiter) {
+            return Some(i + delimiter.len());
+        } else {
+            i += 1;
+        }
+    }
+
+    None
+}
+
+/// Lexes a character literal that starts at `start`.
+/// Returns `None` if the delimiter does not start a character literal.
+fn lex_char(line: &str, start: usize, char_literal: &CharLiteral) -> Option<StringEnd> {
+    let delimiter = char_literal.delimiter.as_str();
+    let body_start = start + delimiter.len();
+    let body = &line[body_start..];
+
+    let body_len = if body.starts_with('\\') {
+        // escape sequences like `\n`, `\'`, and `\u{1F600}` are short
+        body.get(2..)
+            .and_then(|rest| rest.find(delimiter))
+            .filter(|&len| len <= 8)
+            .map(|len| len + 2)
+    } else {
+        body.chars()
+            .next()
+            .filter(|c| !delimiter.starts_with(*c))
+            .map(|c| c.len_utf8())
+    }?;
+
+    if body[body_len..].starts_with(delimiter) {
+        Some(StringEnd::Closed(body_start + body_len + delimiter.len()))
+    } else {
+        None
+    }
+}
+
+/// Lexes a raw string that starts at `start`.
+/// Returns `None` if the prefix does not start a raw string.
+fn lex_raw(line: &str, start: usize, raw: &RawString) -> Option<StringEnd> {
+    let prefix_end = start + raw.prefix.len();
+    let hashes = line[prefix_end..]
+        .bytes()
+        .take_while(|&b| b == b'#')
+        .count();
+    let quote = prefix_end + hashes;
+
+    if line.as_bytes().get(quote) != Some(&b'"') {
+        return None;
+    }
+
+    match find_raw_end(line, quote + 1, hashes) {
+        Some(end) => Some(StringEnd::Closed(end)),
+        None => Some(StringEnd::Open(LexState::RawString(hashes))),
+    }
+}
+
+/// Returns the position right after the `"` and `#`s that end a raw string.
+fn find_raw_end(line: &str, pos: usize, hashes: usize) -> Option<usize> {
+    let bytes = line.as_bytes();
+    (pos..bytes.len())
+        .filter(|&i| bytes[i] == b'"')
+        .find(|&i| {
+            bytes[i + 1..].len() >= hashes
+                && bytes[i + 1..i + 1 + hashes].iter().all(|&b| b == b'#')
+        })
+        .map(|i| i + 1 + hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comments::StringType;
+
+    fn rust_lexer() -> Lexer {
+        Lexer::new(
+            &CommentTypes::new()
+                .add_single("//")
+                .add_block("/*", "*/")
+                .add_string(StringType::new_multiline_quoted("\""))
+                .add_string(StringType::new_char("'"))
+                .add_string(StringType::new_raw("r")),
+        )
+    }
+
+    fn kinds(spans: &[Span], line: &str) -> Vec<(SpanKind, String)> {
+        spans
+            .iter()
+            .map(|s| (s.kind, line[s.start..s.end].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn lex_comments() {
+        let line = "a(); /* b */ c(); // d";
+        let (spans, state) = rust_lexer().lex_line(line, LexState::Code);
+        assert_eq!(LexState::Code, state);
+        assert_eq!(
+            vec![
+                (SpanKind::Code, "a(); ".to_string()),
+                (SpanKind::Comment(1), "/* b */".to_string()),
+                (SpanKind::Code, " c(); ".to_string()),
+                (SpanKind::Comment(0), "// d".to_string()),
+            ],
+            kinds(&spans, line)
+        );
+    }
+
+    #[test]
+    fn lex_string() {
+        let line = r#"let s = "// \" not a comment"; // c"#;
+        let (spans, _) = rust_lexer().lex_line(line, LexState::Code);
+        assert_eq!(
+            vec![
+                (SpanKind::Code, "let s = ".to_string()),
+                (SpanKind::String, r#""// \" not a comment""#.to_string()),
+                (SpanKind::Code, "; ".to_string()),
+                (SpanKind::Comment(0), "// c".to_string()),
+            ],
+            kinds(&spans, line)
+        );
+    }
+
+    #[test]
+    fn lex_raw_string() {
+        let line = r###"let s = r#"/* "not" a comment */"#; r"//";"###;
+        let (spans, state) = rust_lexer().lex_line(line, LexState::Code);
+        assert_eq!(LexState::Code, state);
+        assert!(spans.iter().all(|s| s.kind != SpanKind::Comment(0)));
+        assert!(spans.iter().all(|s| s.kind != SpanKind::Comment(1)));
+    }
+
+    #[test]
+    fn lex_char_and_lifetime() {
+        let line = r#"fn f<'a>(c: &'a char) -> bool { *c == '"' || *c == '\'' } // c"#;
+        let (spans, _) = rust_lexer().lex_line(line, LexState::Code);
+        assert_eq!(
+            (SpanKind::Comment(0), "// c".to_string()),
+            kinds(&spans, line).pop().unwrap()
+        );
+    }
+
+    #[test]
+    fn lex_multiline_string() {
+        let lexer = rust_lexer();
+        let (_, state) = lexer.lex_line(r#"let s = "start"#, LexState::Code);
+        assert_eq!(LexState::String(0), state);
+
+        let line = r#"// still a string" // c"#;
+        let (spans, state) = lexer.lex_line(line, state);
+        assert_eq!(LexState::Code, state);
+        assert!(spans[0].continued);
+        assert_eq!(
+            vec![
+                (SpanKind::String, r#"// still a string""#.to_string()),
+                (SpanKind::Code, " ".to_string()),
+                (SpanKind::Comment(0), "// c".to_string()),
+            ],
+            kinds(&spans, line)
+        );
+    }
+
+    #[test]
+    fn lex_multiline_raw_string() {
+        let lexer = rust_lexer();
+        let (_, state) = lexer.lex_line(r##"let s = r#"start "quote" "##, LexState::Code);
+        assert_eq!(LexState::RawString(1), state);
+
+        let (_, state) = lexer.lex_line(r#"// "still" a string"#, state);
+        assert_eq!(LexState::RawString(1), state);
+
+        let (_, state) = lexer.lex_line(r##"end"#;"##, state);
+        assert_eq!(LexState::Code, state);
+    }
+
+    #[test]
+    fn lex_unclosed_single_line_string() {
+        let lexer = Lexer::new(
+            &CommentTypes::new()
+                .add_single("#")
+                .add_string(StringType::new_quoted("'")),
+        );
+
+        let line = "echo don't # c";
+        let (spans, state) = lexer.lex_line(line, LexState::Code);
+        assert_eq!(LexState::Code, state);
+        assert_eq!(
+            (SpanKind::Comment(0), "# c".to_string()),
+            kinds(&spans, line).pop().unwrap()
+        );
+    }
+
+    #[test]
+    fn lex_block_comment_lines() {
+        let lexer = rust_lexer();
+        let (spans, state) = lexer.lex_line("/* a", LexState::Code);
+        assert_eq!(LexState::BlockComment(1), state);
+        assert!(!spans[0].continued);
+
+        let line = " b */ c";
+        let (spans, state) = lexer.lex_line(line, state);
+        assert_eq!(LexState::Code, state);
+        assert!(spans[0].continued);
+        assert_eq!(
+            vec![
+                (SpanKind::Comment(1), " b */".to_string()),
+                (SpanKind::Code, " c".to_string()),
+            ],
+            kinds(&spans, line)
+        );
+    }
+
+    #[test]
+    fn lex_docstring() {
+        let lexer = Lexer::new(
+            &CommentTypes::new()
+                .add_single("#")
+                .add_block("\"\"\"", "\"\"\"")
+                .add_string(StringType::new_quoted("\"")),
+        );
+
+        let (_, state) = lexer.lex_line("  \"\"\"", LexState::Code);
+        assert_eq!(LexState::BlockComment(1), state);
+
+        let (_, state) = lexer.lex_line("  \"\"\"", state);
+        assert_eq!(LexState::Code, state);
+    }
+}
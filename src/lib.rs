@@ -1,11 +1,19 @@
+pub mod baseline;
+mod blame;
+mod cache;
 pub mod comments;
 mod configs;
 mod custom_tags;
+pub mod diff;
 mod display;
 pub mod format;
+pub mod git;
+pub mod history;
+mod lexer;
 mod maps;
 mod parser;
 mod remover;
+pub mod template;
 pub mod todo;
 
 pub mod errors {
@@ -29,6 +37,12 @@ pub mod errors {
         /// Error for when provided TODO line is not found.
         #[fail(display = "TODO comment not found in line {}", line)]
         TodoNotFound { line: usize },
+        /// Error for when no TODO has the provided ID.
+        #[fail(display = "no TODO with ID '{}'", id)]
+        TodoIdNotFound { id: String },
+        /// Error for when the provided ID prefix matches more than one TODO.
+        #[fail(display = "'{}' matches more than one TODO ID", id)]
+        AmbiguousTodoId { id: String },
         /// Error for when provided default file extension is not supported.
         #[fail(display = "'{}' is an invalid default extension", ext)]
         InvalidDefaultExtension { ext: String },
@@ -38,6 +52,28 @@ pub mod errors {
         /// Error for invalid ignore path.
         #[fail(display = "invalid ignore path: {}", message)]
         InvalidIgnorePath { message: String },
+        /// Error for invalid baseline file.
+        #[fail(display = "invalid baseline file: {}", message)]
+        InvalidBaselineFile { message: String },
+        /// Error for unsupported history step.
+        #[fail(display = "'{}' is an invalid history step", step)]
+        InvalidHistoryStep { step: String },
+        /// Error for a TODO report that cannot be read.
+        #[fail(display = "invalid TODO report: {}", message)]
+        InvalidReport { message: String },
+        /// Error for when running git fails.
+        #[fail(display = "git failed: {}", message)]
+        GitCommandFailed { message: String },
+        /// Error for unsupported output column.
+        #[fail(
+            display = "'{}' is an invalid column. Columns are file, line, end_line, tag, text, \
+                       users, id, author, author_email, commit and date",
+            column
+        )]
+        InvalidColumn { column: String },
+        /// Error for invalid output template.
+        #[fail(display = "invalid template: {}", message)]
+        InvalidTemplate { message: String },
         /// Error for unsupported output format.
         #[fail(display = "invalid output format: {}", message)]
         InvalidOutputFormat { message: String },
@@ -45,20 +81,28 @@ pub mod errors {
 }
 
 use failure::Error;
-use log::debug;
+use fnv::{FnvHashMap, FnvHasher};
+use log::{debug, warn};
 use serde::ser::{Serialize, SerializeSeq, Serializer};
 use std::borrow::Cow;
-use std::fs::File;
+use std::cmp::Ordering;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
 use std::io::{self, BufReader, Cursor, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use crate::cache::{hash_content, FileStamp, TodoCache};
 use crate::comments::CommentTypes;
 use crate::configs::TodoRConfigFileSerial;
 use crate::display::{write_file_todos, TodoRStyles};
 use crate::errors::TodoRError;
+use crate::format::{Column, Severity};
+use crate::lexer::Lexer;
 use crate::maps::CommentRegexMultiMap;
 use crate::parser::{parse_content, parse_content_with_filter};
-use crate::todo::{PathedTodo, Todo, TodoFile};
+use crate::template::{Template, TemplateFormat};
+use crate::todo::{todo_id, PathedTodo, Todo, TodoFile};
 
 static DEFAULT_CONFIG: &str = include_str!("default_config.json");
 static EXAMPLE_CONFIG: &str = include_str!("example_config.hjson");
@@ -80,6 +124,12 @@ pub struct TodoRBuilder {
     override_tags: Option<Vec<String>>,
     override_default_ext: Option<String>,
     override_styles: Option<TodoRStyles>,
+    override_trailing_comments: Option<bool>,
+    override_cache: Option<bool>,
+    show_ids: bool,
+    columns: Option<Vec<Column>>,
+    template: Option<String>,
+    cache_file: Option<PathBuf>,
     // Config from files. Parameters with override_ override inner_config.
     inner_config: config::Config,
 }
@@ -106,6 +156,20 @@ impl TodoRBuilder {
 
     /// Consumes self and builds TodoR.
     pub fn build(self) -> Result<TodoR, Error> {
+        let (config, writer_config) = self.build_configs()?;
+        let mut todor = TodoR::with_parser(TodoParser { config });
+        todor.writer_config = writer_config;
+        Ok(todor)
+    }
+
+    /// Consumes self and builds a TodoParser that can be shared between threads.
+    pub fn build_parser(self) -> Result<TodoParser, Error> {
+        let (config, _) = self.build_configs()?;
+        Ok(TodoParser { config })
+    }
+
+    /// Consumes self and builds the config for finding TODOs and the config for writing them.
+    fn build_configs(self) -> Result<(TodoRConfig, TodoRWriterConfig), Error> {
         let config_struct: TodoRConfigFileSerial =
             self.inner_config
                 .try_into()
@@ -121,12 +185,24 @@ impl TodoRBuilder {
             .override_default_ext
             .unwrap_or_else(|| config_struct.default_ext.to_owned());
         tags.append(&mut self.added_tags.clone());
+        let trailing_comments = self
+            .override_trailing_comments
+            .unwrap_or(config_struct.trailing_comments);
 
         let config_styles = config_struct.styles;
         let styles = self
             .override_styles
             .unwrap_or(config_styles.into_todo_r_styles()?);
 
+        let use_cache = self.override_cache.unwrap_or(config_struct.cache);
+
+        // Hash everything that changes which TODOs are found so old caches are not used
+        let mut config_hasher = FnvHasher::default();
+        env!("CARGO_PKG_VERSION").hash(&mut config_hasher);
+        tags.hash(&mut config_hasher);
+        default_ext.hash(&mut config_hasher);
+        trailing_comments.hash(&mut config_hasher);
+
         let mut ext_to_regexs = CommentRegexMultiMap::new(CommentTypes::new().add_single("#"));
         // Iter over default comment types
         for comment_config in config_struct
@@ -135,6 +211,7 @@ impl TodoRBuilder {
             // Iter over config comment types
             .chain(config_struct.comments.into_iter())
         {
+            format!("{:?}", comment_config).hash(&mut config_hasher);
             let (config_ext, config_exts, comment_types) = comment_config.break_apart();
             let exts = config_exts.into_iter().flatten().chain(config_ext);
 
@@ -146,15 +223,66 @@ impl TodoRBuilder {
             .reset_fallback_key(&default_ext)
             .ok_or(TodoRError::InvalidDefaultExtension { ext: default_ext })?;
 
+        let cache = match self.cache_file {
+            Some(cache_file) if use_cache => Some(Arc::new(TodoCache::load(
+                cache_file,
+                config_hasher.finish(),
+            ))),
+            _ => None,
+        };
+
+        let template = match self.template {
+            Some(template) => match config_struct
+                .formats
+                .into_iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(&template))
+            {
+                Some((_, format_config)) => Some(format_config.into_template_format()?),
+                None if !template.contains('{') => {
+                    return Err(TodoRError::InvalidTemplate {
+                        message: format!("no template named '{}' in the formats config", template),
+                    }
+                    .into())
+                }
+                None => Some(TemplateFormat::new(Template::parse(&template)?)),
+            },
+            None => None,
+        };
+
         let config = TodoRConfig {
             tags,
             styles,
+            trailing_comments,
             ext_to_regexs,
+            cache,
+        };
+        let writer_config = TodoRWriterConfig {
+            show_ids: self.show_ids,
+            columns: self.columns,
+            template,
+            severities: config_struct
+                .severity
+                .into_iter()
+                .map(|(tag, severity)| (tag.to_uppercase(), severity))
+                .collect(),
         };
 
         debug!("todor parser built: {:?}", config);
 
-        Ok(TodoR::with_config(config))
+        Ok((config, writer_config))
+    }
+
+    /// Gets the names of the files or directories that mark the root of a workspace.
+    /// Returns an empty list if the config does not have any.
+    pub fn root_markers(&self) -> Result<Vec<String>, Error> {
+        match self.inner_config.get("root_markers") {
+            Ok(markers) => Ok(markers),
+            Err(config::ConfigError::NotFound(_)) => Ok(Vec::new()),
+            Err(err) => Err(TodoRError::InvalidConfigFile {
+                message: err.to_string(),
+            }
+            .into()),
+        }
     }
 
     /// Adds config file for TodoR.
@@ -176,6 +304,17 @@ impl TodoRBuilder {
         Ok(self)
     }
 
+    /// Adds config for TodoR from the content of a config file in the provided format.
+    pub fn add_config_str(
+        &mut self,
+        config: &str,
+        format: config::FileFormat,
+    ) -> Result<&mut Self, Error> {
+        self.inner_config
+            .merge(config::File::from_str(config, format))?;
+        Ok(self)
+    }
+
     /// Adds tag for TodoR to look for without overriding tags from config files.
     pub fn add_tag<'a, S: Into<Cow<'a, str>>>(&mut self, tag: S) -> &mut Self {
         self.added_tags.push(tag.into().into_owned());
@@ -230,6 +369,50 @@ impl TodoRBuilder {
 
         Ok(())
     }
+
+    /// Sets whether TODOs in comments that come after code on the same line are found.
+    /// This overrides the `trailing_comments` setting from config files.
+    pub fn set_trailing_comments(&mut self, trailing_comments: bool) -> &mut Self {
+        self.override_trailing_comments = Some(trailing_comments);
+        self
+    }
+
+    /// Sets the file used to cache found TODOs between runs.
+    /// Files whose size and modified time have not changed are not parsed again.
+    ///
+    /// The cache is only used if it is turned on with `set_cache()` or the `cache` config setting.
+    pub fn set_cache_file(&mut self, cache_file: impl AsRef<Path>) -> &mut Self {
+        self.cache_file = Some(cache_file.as_ref().to_owned());
+        self
+    }
+
+    /// Sets whether TODO IDs are shown in the default output.
+    pub fn set_show_ids(&mut self, show_ids: bool) -> &mut Self {
+        self.show_ids = show_ids;
+        self
+    }
+
+    /// Sets the columns written by the `csv` and `tsv` formats and their order.
+    pub fn set_columns(&mut self, columns: Vec<Column>) -> &mut Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    /// Sets the template used by the `template` format.
+    ///
+    /// If template is the name of a template under `formats` in the config, that template is used.
+    /// Otherwise template is rendered for every TODO, like `{file}:{line}: [{tag}] {text}`.
+    pub fn set_template(&mut self, template: &str) -> &mut Self {
+        self.template = Some(template.to_string());
+        self
+    }
+
+    /// Sets whether the cache file is used.
+    /// This overrides the `cache` setting from config files.
+    pub fn set_cache(&mut self, cache: bool) -> &mut Self {
+        self.override_cache = Some(cache);
+        self
+    }
 }
 
 /// Writes the default configuration file to out_buffer.
@@ -241,18 +424,216 @@ pub fn write_example_config(out_buffer: &mut impl Write) -> Result<(), Error> {
 /// Configuration for `TodoR`.
 ///
 /// `tags` gives a list of the TODO terms to search for.
+/// `trailing_comments` sets if comments after code on the same line are searched.
+/// `cache` holds TODOs found on previous runs if caching is turned on.
 #[derive(Debug, Clone)]
 struct TodoRConfig {
     tags: Vec<String>,
     styles: TodoRStyles,
+    trailing_comments: bool,
     ext_to_regexs: CommentRegexMultiMap<String>,
+    cache: Option<Arc<TodoCache>>,
+}
+
+/// Configuration for writing the TODOs found by `TodoR`.
+///
+/// These settings do not change which TODOs are found, so they are not part of `TodoParser`.
+#[derive(Debug, Clone, Default)]
+struct TodoRWriterConfig {
+    show_ids: bool,
+    columns: Option<Vec<Column>>,
+    template: Option<TemplateFormat>,
+    // Severity of each tag in uppercase
+    severities: FnvHashMap<String, Severity>,
+}
+
+/// Parser for finding TODOs in comments.
+///
+/// TodoParser holds the compiled configuration and never changes after it is built,
+/// so it is `Send + Sync` and can parse files from several threads at once.
+#[derive(Debug, Clone)]
+pub struct TodoParser {
+    config: TodoRConfig,
+}
+
+impl TodoParser {
+    /// Opens file at given filepath and returns a TodoFile with all its TODOs.
+    pub fn parse_file<F>(&self, filepath: F) -> Result<TodoFile, Error>
+    where
+        F: AsRef<Path>,
+    {
+        // using _p just to let the compiler know the correct type for parse_option_filtered_file()
+        let mut _p = Some(|_t: &Todo| true);
+        _p = None;
+        self.parse_option_filtered_file(filepath, &_p)
+    }
+
+    /// Opens file at given filepath and returns a TodoFile with all its TODOs.
+    /// If pred is not None, only TODOs that satisfy pred are kept.
+    pub fn parse_option_filtered_file<P, F>(
+        &self,
+        filepath: F,
+        pred: &Option<P>,
+    ) -> Result<TodoFile, Error>
+    where
+        P: Fn(&Todo) -> bool,
+        F: AsRef<Path>,
+    {
+        let filepath = filepath.as_ref();
+        let mut todo_file = TodoFile::new(filepath);
+
+        // Make sure the file is not a directory
+        if !filepath.is_file() {
+            if filepath.is_dir() {
+                return Err(TodoRError::InputIsDir {
+                    filepath: filepath.to_string_lossy().to_string(),
+                }
+                .into());
+            } else {
+                return Err(TodoRError::CannotAccessFile {
+                    filepath: filepath.to_string_lossy().to_string(),
+                }
+                .into());
+            }
+        }
+
+        let file_ext = match filepath.extension() {
+            Some(ext) => ext.to_str().unwrap(),
+            // lots of shell files have no extension
+            None => "sh",
+        };
+        let parser_regexs = self.config.ext_to_regexs.get(file_ext, &self.config.tags);
+        let trailing_comments = self.config.trailing_comments;
+
+        if let Some(cache) = &self.config.cache {
+            // cache holds every TODO so pred is applied after
+            let mut todos = self.parse_file_with_cache(filepath, file_ext, cache)?;
+            if let Some(p) = pred {
+                todos.retain(|t| p(t));
+            }
+            todo_file.set_todos(todos);
+        } else {
+            let file = File::open(filepath)?;
+            let mut file_reader = BufReader::new(file);
+            todo_file.set_todos(match pred {
+                Some(p) => parse_content_with_filter(
+                    &mut file_reader,
+                    parser_regexs,
+                    trailing_comments,
+                    p,
+                )?,
+                None => parse_content(&mut file_reader, parser_regexs, trailing_comments)?,
+            });
+        }
+
+        debug!(
+            "found {} TODOs in `{}`",
+            todo_file.len(),
+            filepath.display()
+        );
+
+        Ok(todo_file)
+    }
+
+    /// Gets the TODOs in filepath from cache if the file is unchanged and parses it otherwise.
+    fn parse_file_with_cache(
+        &self,
+        filepath: &Path,
+        file_ext: &str,
+        cache: &TodoCache,
+    ) -> Result<Vec<Todo>, Error> {
+        let key = cache.key(filepath);
+        let stamp = FileStamp::new(&filepath.metadata()?);
+        if let Some(todos) = cache.get_unchanged(&key, &stamp) {
+            debug!("using cached TODOs for `{}`", filepath.display());
+            return Ok(todos);
+        }
+
+        let content = fs::read(filepath)?;
+        let content_hash = hash_content(&content);
+        let todos = match cache.get_same_content(&key, content_hash) {
+            Some(todos) => todos,
+            None => {
+                let parser_regexs = self.config.ext_to_regexs.get(file_ext, &self.config.tags);
+                parse_content(
+                    &mut Cursor::new(content),
+                    parser_regexs,
+                    self.config.trailing_comments,
+                )?
+            }
+        };
+
+        cache.insert(key, stamp, content_hash, &todos);
+        Ok(todos)
+    }
+
+    /// Writes TODOs found by this parser to the cache file so later runs can skip unchanged files.
+    /// Does nothing if caching is turned off.
+    pub fn save_cache(&self) -> Result<(), Error> {
+        match &self.config.cache {
+            Some(cache) => cache.save(),
+            None => Ok(()),
+        }
+    }
+
+    /// Gets the lexer for the comment types of the file at filepath.
+    fn lexer_for(&self, filepath: &Path) -> &Lexer {
+        let file_ext = match filepath.extension() {
+            Some(ext) => ext.to_str().unwrap_or("sh"),
+            // lots of shell files have no extension
+            None => "sh",
+        };
+        self.config
+            .ext_to_regexs
+            .get(file_ext, &self.config.tags)
+            .lexer()
+    }
+
+    /// Finds TODO comments in the given content as if it were in a file with extension ext.
+    /// The returned TodoFile has an empty filepath.
+    pub fn parse_content(&self, content: &str, ext: &str) -> Result<TodoFile, Error> {
+        let mut todo_file = TodoFile::new("");
+        let mut content_buf = Cursor::new(content);
+        let parser_regexs = self.config.ext_to_regexs.get(ext, &self.config.tags);
+
+        todo_file.set_todos(parse_content(
+            &mut content_buf,
+            parser_regexs,
+            self.config.trailing_comments,
+        )?);
+
+        Ok(todo_file)
+    }
+
+    /// Finds TODO comments in the given content as if it were the content of the file at filepath.
+    /// The file is not read so filepath does not need to exist.
+    pub fn parse_content_at<F>(&self, content: &str, filepath: F) -> Result<TodoFile, Error>
+    where
+        F: AsRef<Path>,
+    {
+        let filepath = filepath.as_ref();
+        let file_ext = match filepath.extension() {
+            Some(ext) => ext.to_str().unwrap_or("sh"),
+            // lots of shell files have no extension
+            None => "sh",
+        };
+
+        let mut todo_file = self.parse_content(content, file_ext)?;
+        todo_file.filepath = filepath.to_path_buf();
+        Ok(todo_file)
+    }
 }
 
 /// Parser for finding TODOs in comments and storing them on a per-file basis.
+///
+/// The TODOs found are stored in TodoR while the parsing is done by a shared TodoParser.
 #[derive(Debug, Clone)]
 pub struct TodoR {
-    config: TodoRConfig,
+    parser: Arc<TodoParser>,
+    writer_config: TodoRWriterConfig,
     todo_files: Vec<TodoFile>,
+    // Absolute path of the workspace root that TODO IDs are made relative to
+    root: Option<PathBuf>,
 }
 
 impl<'a> Default for TodoR {
@@ -277,14 +658,36 @@ impl TodoR {
         builder.build().unwrap()
     }
 
-    /// Creates new TodoR using given configuration.
-    fn with_config(config: TodoRConfig) -> TodoR {
+    /// Creates new TodoR that stores TODOs found by parser.
+    /// TODOs are written with the default output settings.
+    pub fn with_parser(parser: impl Into<Arc<TodoParser>>) -> TodoR {
         TodoR {
-            config,
+            parser: parser.into(),
+            writer_config: TodoRWriterConfig::default(),
             todo_files: Vec::new(),
+            root: None,
         }
     }
 
+    /// Returns the TodoParser used to find TODOs.
+    pub fn parser(&self) -> &Arc<TodoParser> {
+        &self.parser
+    }
+
+    /// Sets the workspace root that TODO IDs are made relative to.
+    /// The same TODO then has the same ID wherever todor is run from.
+    ///
+    /// Without a root, IDs are made from file paths as they are given.
+    pub fn set_root(&mut self, root: impl AsRef<Path>) {
+        let curr_dir = std::env::current_dir().unwrap_or_default();
+        self.root = Some(cache::normalize_path(&curr_dir.join(root)));
+    }
+
+    /// Returns the workspace root that TODO IDs are made relative to.
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
     /// Returns the number of files currently tracked by TodoR
     pub fn num_files(&self) -> usize {
         self.todo_files.len()
@@ -317,10 +720,9 @@ impl TodoR {
     where
         F: AsRef<Path>,
     {
-        // using _p just to let the compiler know the correct type for open_option_filtered_todos()
-        let mut _p = Some(|_t: &Todo| true);
-        _p = None;
-        self.open_option_filtered_todos(filepath, &_p)
+        let todo_file = self.parser.parse_file(filepath)?;
+        self.todo_files.push(todo_file);
+        Ok(())
     }
 
     /// Opens file at given filepath and process it by finding all its TODOs.
@@ -346,60 +748,110 @@ impl TodoR {
         P: Fn(&Todo) -> bool,
         F: AsRef<Path>,
     {
-        let filepath = filepath.as_ref();
-        let mut todo_file = TodoFile::new(filepath);
-
-        // Make sure the file is not a directory
-        if !filepath.is_file() {
-            if filepath.is_dir() {
-                return Err(TodoRError::InputIsDir {
-                    filepath: filepath.to_string_lossy().to_string(),
-                }
-                .into());
-            } else {
-                return Err(TodoRError::CannotAccessFile {
-                    filepath: filepath.to_string_lossy().to_string(),
-                }
-                .into());
-            }
-        }
-
-        let file_ext = match filepath.extension() {
-            Some(ext) => ext.to_str().unwrap(),
-            // lots of shell files have no extension
-            None => "sh",
-        };
-        let parser_regexs = self.config.ext_to_regexs.get(file_ext, &self.config.tags);
-
-        let file = File::open(filepath)?;
-        let mut file_reader = BufReader::new(file);
-        todo_file.set_todos(match pred {
-            Some(p) => parse_content_with_filter(&mut file_reader, &parser_regexs, p)?,
-            None => parse_content(&mut file_reader, &parser_regexs)?,
-        });
-
-        debug!(
-            "found {} TODOs in `{}`",
-            todo_file.len(),
-            filepath.display()
-        );
-
+        let todo_file = self.parser.parse_option_filtered_file(filepath, pred)?;
         self.todo_files.push(todo_file);
         Ok(())
     }
 
     /// Finds TODO comments in the given content
     pub fn find_todos(&mut self, content: &str, ext: &str) -> Result<(), Error> {
-        let mut todo_file = TodoFile::new("");
-        let mut content_buf = Cursor::new(content);
-        let parser_regexs = self.config.ext_to_regexs.get(ext, &self.config.tags);
+        let todo_file = self.parser.parse_content(content, ext)?;
+        self.todo_files.push(todo_file);
+        Ok(())
+    }
 
-        todo_file.set_todos(parse_content(&mut content_buf, &parser_regexs)?);
+    /// Gets the Severity set for tag in the config. Tags that are not set are warnings.
+    pub fn tag_severity(&self, tag: &str) -> Severity {
+        self.writer_config
+            .severities
+            .get(&tag.to_uppercase())
+            .copied()
+            .unwrap_or_default()
+    }
 
+    /// Finds TODO comments in the given content and tracks them as the TODOs of the file at filepath.
+    /// The file is not read so this works for content from other sources like git.
+    pub fn find_todos_at<F>(&mut self, content: &str, filepath: F) -> Result<(), Error>
+    where
+        F: AsRef<Path>,
+    {
+        let todo_file = self.parser.parse_content_at(content, filepath)?;
         self.todo_files.push(todo_file);
         Ok(())
     }
 
+    /// Adds a TodoFile found separately, such as by a TodoParser on another thread.
+    pub fn add_todo_file(&mut self, todo_file: TodoFile) {
+        self.todo_files.push(todo_file);
+    }
+
+    /// Attaches git blame information to every tracked TODO.
+    /// Files that git cannot blame, like untracked files, are skipped with a warning.
+    pub fn add_blame(&mut self) {
+        for todo_file in &mut self.todo_files {
+            if let Err(err) = todo_file.add_blame() {
+                warn!("cannot blame `{}`: {}", todo_file.filepath.display(), err);
+            }
+        }
+    }
+
+    /// Removes tracked TODOs that do not satisfy pred.
+    /// pred is given the path of the file each TODO is in.
+    pub fn retain_todos<P>(&mut self, mut pred: P)
+    where
+        P: FnMut(&Path, &Todo) -> bool,
+    {
+        for todo_file in &mut self.todo_files {
+            let filepath = &todo_file.filepath;
+            todo_file.todos.retain(|todo| pred(filepath, todo));
+        }
+    }
+
+    /// Replaces the tracked file that has the same filepath as todo_file.
+    /// If that file is not tracked yet, todo_file is added instead.
+    ///
+    /// Returns the TodoFile that was replaced.
+    pub fn replace_todo_file(&mut self, todo_file: TodoFile) -> Option<TodoFile> {
+        match self
+            .todo_files
+            .iter_mut()
+            .find(|tf| tf.filepath == todo_file.filepath)
+        {
+            Some(tracked) => Some(std::mem::replace(tracked, todo_file)),
+            None => {
+                self.todo_files.push(todo_file);
+                None
+            }
+        }
+    }
+
+    /// Stops tracking the file at filepath.
+    ///
+    /// Returns the TodoFile that was removed or None if the file was not tracked.
+    pub fn remove_todo_file(&mut self, filepath: &Path) -> Option<TodoFile> {
+        let i = self
+            .todo_files
+            .iter()
+            .position(|tf| tf.filepath == filepath)?;
+        Some(self.todo_files.remove(i))
+    }
+
+    /// Moves all files tracked by other into this TodoR.
+    ///
+    /// This lets TodoRs that searched different files on separate threads be combined.
+    pub fn merge(&mut self, mut other: TodoR) {
+        self.todo_files.append(&mut other.todo_files);
+    }
+
+    /// Sorts tracked files by comparing their filepaths with compare.
+    pub fn sort_files_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&Path, &Path) -> Ordering,
+    {
+        self.todo_files
+            .sort_by(|a, b| compare(&a.filepath, &b.filepath));
+    }
+
     /// Prints TODOs to stdout.
     pub fn print_todos(&self) {
         // lock stdout to print faster
@@ -413,7 +865,13 @@ impl TodoR {
     /// Writes TODOs to out_buffer.
     pub fn write_todos(&self, out_buffer: &mut impl Write) -> Result<(), Error> {
         for todo_file in &self.todo_files {
-            write_file_todos(out_buffer, &todo_file, &self.config.styles)?;
+            write_file_todos(
+                out_buffer,
+                &todo_file,
+                &self.parser.config.styles,
+                self.writer_config.show_ids,
+                self.root(),
+            )?;
         }
 
         Ok(())
@@ -428,7 +886,13 @@ impl TodoR {
     ) -> Result<(), Error> {
         for todo_file in &self.todo_files {
             if todo_file.filepath == filepath {
-                write_file_todos(out_buffer, &todo_file, &self.config.styles)?;
+                write_file_todos(
+                    out_buffer,
+                    &todo_file,
+                    &self.parser.config.styles,
+                    self.writer_config.show_ids,
+                    self.root(),
+                )?;
                 break;
             }
         }
@@ -436,16 +900,25 @@ impl TodoR {
         Ok(())
     }
 
+    /// Returns an iterator over all tracked files.
+    pub fn iter_files(&self) -> impl Iterator<Item = &TodoFile> {
+        self.todo_files.iter()
+    }
+
     /// Returns an iterator that Iterates over tracked TODOs along with the
     pub fn iter(&self) -> impl Iterator<Item = PathedTodo> {
-        self.todo_files.iter().flatten()
+        let root = self.root();
+        self.todo_files
+            .iter()
+            .flat_map(move |todo_file| todo_file.iter_in_root(root))
     }
 
     /// Deletes TODO line from given filepath corresponding to the given index.
     pub fn remove_todo(&mut self, filepath: &Path, todo_index: usize) -> Result<(), Error> {
         for mut todo_file in &mut self.todo_files {
             if filepath == todo_file.filepath {
-                remover::remove_todo_by_index(&mut todo_file, todo_index)?;
+                let lexer = self.parser.lexer_for(filepath);
+                remover::remove_todo_by_index(&mut todo_file, todo_index, lexer)?;
                 return Ok(());
             }
         }
@@ -456,11 +929,45 @@ impl TodoR {
         .into())
     }
 
+    /// Returns the TODO whose ID starts with id.
+    /// Fails if no TODO or more than one TODO matches.
+    pub fn find_todo_by_id(&self, id: &str) -> Result<PathedTodo<'_>, Error> {
+        let mut matches = self
+            .iter()
+            .filter(|ptodo| !id.is_empty() && ptodo.id().starts_with(id));
+
+        match (matches.next(), matches.next()) {
+            (Some(ptodo), None) => Ok(ptodo),
+            (Some(_), Some(_)) => Err(TodoRError::AmbiguousTodoId { id: id.to_string() }.into()),
+            (None, _) => Err(TodoRError::TodoIdNotFound { id: id.to_string() }.into()),
+        }
+    }
+
+    /// Deletes the TODO whose ID starts with id.
+    pub fn remove_todo_by_id(&mut self, id: &str) -> Result<(), Error> {
+        let full_id = self.find_todo_by_id(id)?.id();
+        for todo_file in &mut self.todo_files {
+            let filepath = &todo_file.filepath;
+            let todo_index = todo_file
+                .todos
+                .iter()
+                .position(|todo| todo_id(filepath, todo, self.root.as_deref()) == full_id);
+
+            if let Some(todo_index) = todo_index {
+                let lexer = self.parser.lexer_for(filepath);
+                return remover::remove_todo_by_index(todo_file, todo_index, lexer);
+            }
+        }
+
+        Err(TodoRError::TodoIdNotFound { id: id.to_string() }.into())
+    }
+
     /// Deletes TODO line from given filepath corresponding to the given line.
     pub fn remove_todo_line(&mut self, filepath: &Path, line: usize) -> Result<(), Error> {
         for mut todo_file in &mut self.todo_files {
             if filepath == todo_file.filepath {
-                remover::remove_todo_by_line(&mut todo_file, line)?;
+                let lexer = self.parser.lexer_for(filepath);
+                remover::remove_todo_by_line(&mut todo_file, line, lexer)?;
 
                 return Ok(());
             }
@@ -485,3 +992,104 @@ impl Serialize for TodoR {
         seq.end()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn parser_is_send_sync() {
+        assert_send_sync::<TodoParser>();
+        assert_send_sync::<TodoR>();
+    }
+
+    #[test]
+    fn parse_content_from_threads() {
+        let mut builder = TodoRBuilder::new();
+        builder.add_override_tags(vec!["TODO"]);
+        let parser = builder.build_parser().unwrap();
+
+        let (rs_file, py_file) = thread::scope(|s| {
+            let rs = s.spawn(|| parser.parse_content("// TODO: rs item", "rs").unwrap());
+            let py = s.spawn(|| parser.parse_content("# TODO: py item", "py").unwrap());
+            (rs.join().unwrap(), py.join().unwrap())
+        });
+
+        assert_eq!(rs_file.todos.len(), 1);
+        assert_eq!(rs_file.todos[0].content, "rs item");
+        assert_eq!(py_file.todos.len(), 1);
+        assert_eq!(py_file.todos[0].content, "py item");
+    }
+
+    #[test]
+    fn todor_collects_parsed_files() {
+        let parser = Arc::new(TodoRBuilder::new().build_parser().unwrap());
+        let mut todor = TodoR::with_parser(parser.clone());
+
+        todor.add_todo_file(parser.parse_content("// TODO: item", "rs").unwrap());
+        todor.find_todos("# TODO: item", "py").unwrap();
+
+        assert_eq!(todor.num_files(), 2);
+        assert_eq!(todor.num_todos(), 2);
+    }
+
+    #[test]
+    fn find_todo_by_id() {
+        let mut todor = TodoR::new();
+        let mut todo_file = todor
+            .parser()
+            .parse_content("// TODO: item\n// TODO: item\n// TODO: other\n", "rs")
+            .unwrap();
+        todo_file.filepath = PathBuf::from("a.rs");
+        todor.add_todo_file(todo_file);
+
+        let ids: Vec<String> = todor.iter().map(|ptodo| ptodo.id()).collect();
+        assert_ne!(ids[0], ids[1]);
+        assert_eq!(todor.find_todo_by_id(&ids[1]).unwrap().todo().line, 2);
+        assert_eq!(todor.find_todo_by_id(&ids[2][..8]).unwrap().todo().line, 3);
+        assert!(todor.find_todo_by_id("").is_err());
+        assert!(todor.find_todo_by_id("not an id").is_err());
+    }
+
+    #[test]
+    fn tag_severity() {
+        let mut builder = TodoRBuilder::new();
+        builder
+            .add_config_str(
+                r#"{"severity": {"fixme": "error"}}"#,
+                config::FileFormat::Json,
+            )
+            .unwrap();
+        let todor = builder.build().unwrap();
+
+        assert_eq!(todor.tag_severity("FIXME"), Severity::Error);
+        assert_eq!(todor.tag_severity("todo"), Severity::Warning);
+    }
+
+    #[test]
+    fn replace_and_remove_todo_file() {
+        let parser = TodoRBuilder::new().build_parser().unwrap();
+        let mut todor = TodoR::with_parser(parser);
+        let mut todo_file = TodoFile::new("a.rs");
+        todo_file.set_todos(vec![Todo::new(1, "TODO", "item")]);
+
+        assert!(todor.replace_todo_file(todo_file.clone()).is_none());
+        assert_eq!(todor.num_todos(), 1);
+
+        todo_file.set_todos(vec![
+            Todo::new(1, "TODO", "item"),
+            Todo::new(2, "TODO", "item2"),
+        ]);
+        let old = todor.replace_todo_file(todo_file).unwrap();
+        assert_eq!(old.len(), 1);
+        assert_eq!(todor.num_files(), 1);
+        assert_eq!(todor.num_todos(), 2);
+
+        assert!(todor.remove_todo_file(Path::new("b.rs")).is_none());
+        assert_eq!(todor.remove_todo_file(Path::new("a.rs")).unwrap().len(), 2);
+        assert_eq!(todor.num_files(), 0);
+    }
+}
@@ -1,12 +1,12 @@
 // Module for CommentMapStruct
 
 use fnv::FnvHashMap;
-use regex::Regex;
 use std::borrow::Borrow;
 use std::hash::Hash;
+use std::sync::OnceLock;
 
 use crate::comments::CommentTypes;
-use crate::parser::build_parser_regexs;
+use crate::parser::{build_parser_regexs, ParserRegexs};
 
 /// FallbackHashMap is a Hashmap that yields a fallback value for `get(k)` if `k` has not been
 /// inserted.
@@ -52,9 +52,12 @@ impl<K: Hash + Eq, V> FallbackHashMap<K, V> {
 }
 
 /// Hashmap that does not need to copy values for two keys to have same value.
-/// Also, it converts `CommentTypes` into `Vec<Regex>` and caches the value to avoid
+/// Also, it converts `CommentTypes` into `ParserRegexs` and caches the value to avoid
 /// repeated conversions.
 ///
+/// ParserRegexs are built at most once per value using a `OnceLock`, so getting them only needs
+/// `&self` and CommentRegexMultiMap can be shared between threads.
+///
 /// Note that CommentRegexMultiMap is not designed to remove items or repeatedly change values for
 /// a given key.
 /// This is because values are not reference counted and thus cannot be discarded once added.
@@ -63,7 +66,7 @@ impl<K: Hash + Eq, V> FallbackHashMap<K, V> {
 pub struct CommentRegexMultiMap<K: Hash + Eq> {
     map: FallbackHashMap<K, usize>,
     comment_types: Vec<CommentTypes>,
-    regexs: Vec<Option<Vec<Regex>>>,
+    regexs: Vec<OnceLock<ParserRegexs>>,
 }
 
 impl<K: Hash + Eq> CommentRegexMultiMap<K> {
@@ -73,7 +76,7 @@ impl<K: Hash + Eq> CommentRegexMultiMap<K> {
         comment_types.push(fallback_value);
 
         let mut regexs = Vec::new();
-        regexs.push(None);
+        regexs.push(OnceLock::new());
 
         CommentRegexMultiMap {
             map: FallbackHashMap::new(0),
@@ -89,7 +92,7 @@ impl<K: Hash + Eq> CommentRegexMultiMap<K> {
         let i = self.comment_types.len();
         self.map.insert(k, i);
         self.comment_types.push(v);
-        self.regexs.push(None);
+        self.regexs.push(OnceLock::new());
     }
 
     /// Inserts value `v` for all keys in `ks`
@@ -99,25 +102,24 @@ impl<K: Hash + Eq> CommentRegexMultiMap<K> {
             self.map.insert(k, i);
         }
         self.comment_types.push(v);
-        self.regexs.push(None);
+        self.regexs.push(OnceLock::new());
     }
 
-    /// Gets the the Vec<Regex> built from the inserted CommentTypes for key `k`.
-    /// The Vec<Regex> is cached so the regexs do not need to be rebuilt.
+    /// Gets the the ParserRegexs built from the inserted CommentTypes for key `k`.
+    /// The ParserRegexs is cached so the regexs do not need to be rebuilt.
     /// If `k` has not been inserted, fallback value is returned
-    pub fn get<Q: ?Sized>(&mut self, k: &Q, tags: &[String]) -> &Vec<Regex>
+    pub fn get<Q: ?Sized>(&self, k: &Q, tags: &[String]) -> &ParserRegexs
     where
         K: Borrow<Q>,
         Q: Hash + Eq,
     {
         let v_i = *self.map.get(k);
-        let comment_types = &self.comment_types;
-        self.regexs[v_i].get_or_insert_with(|| build_parser_regexs(&comment_types[v_i], tags))
+        self.regexs[v_i].get_or_init(|| build_parser_regexs(&self.comment_types[v_i], tags))
     }
 
     /// Same as `get()` except it does not fallback if the key is not found.
     #[allow(dead_code)]
-    pub fn get_without_fallback<Q: ?Sized>(&mut self, k: &Q, tags: &[String]) -> Option<&Vec<Regex>>
+    pub fn get_without_fallback<Q: ?Sized>(&self, k: &Q, tags: &[String]) -> Option<&ParserRegexs>
     where
         K: Borrow<Q>,
         Q: Hash + Eq,
@@ -125,10 +127,9 @@ impl<K: Hash + Eq> CommentRegexMultiMap<K> {
         match self.map.get_without_fallback(k) {
             Some(pv_i) => {
                 let v_i = *pv_i;
-                let comment_types = &self.comment_types;
                 Some(
                     self.regexs[v_i]
-                        .get_or_insert_with(|| build_parser_regexs(&comment_types[v_i], tags)),
+                        .get_or_init(|| build_parser_regexs(&self.comment_types[v_i], tags)),
                 )
             }
             None => None,
@@ -139,7 +140,7 @@ impl<K: Hash + Eq> CommentRegexMultiMap<K> {
     #[allow(dead_code)]
     pub fn reset_fallback_value(&mut self, new_fallback_value: CommentTypes) {
         self.comment_types[0] = new_fallback_value;
-        self.regexs[0] = None;
+        self.regexs[0] = OnceLock::new();
     }
 
     /// Resets the fallback value to the one given by `new_fallback_key`.
@@ -154,7 +155,7 @@ impl<K: Hash + Eq> CommentRegexMultiMap<K> {
                 let v_i = *pv_i;
                 if v_i != 0 {
                     self.comment_types[0] = self.comment_types[v_i].clone();
-                    self.regexs[0] = None;
+                    self.regexs[0] = OnceLock::new();
                 }
                 Some(&self.comment_types[0])
             }
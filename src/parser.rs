@@ -1,96 +1,292 @@
 // Module for finding TODOs in files
 
+use aho_corasick::AhoCorasick;
+use aho_corasick::AhoCorasickBuilder;
 use log::trace;
-use regex::Regex;
+use regex::{escape, Captures, Regex};
 use std::borrow::Cow;
 use std::io::BufRead;
 
-use crate::comments::CommentTypes;
-use crate::custom_tags::get_regex_for_comment;
-use crate::todo::Todo;
+use crate::comments::{CommentType, CommentTypes};
+use crate::custom_tags::{
+    get_regex_for_block_content, get_regex_for_comment, get_regex_for_continuation,
+};
+use crate::lexer::{LexState, Lexer, SpanKind};
+use crate::todo::{OccurrenceCounter, Todo};
 
-/// Builds Regexs for use with parse_content.
-pub fn build_parser_regexs(comment_types: &CommentTypes, tags: &[String]) -> Vec<Regex> {
-    comment_types
-        .iter()
-        .map(|c| get_regex_for_comment(tags, c).unwrap())
-        .collect()
+/// Regexs and tokens built from a `CommentType` for use with parse_content.
+#[derive(Debug, Clone)]
+pub struct CommentRegex {
+    /// Matches TODOs in comments that start at the beginning of the text.
+    line: Regex,
+    /// Matches TODOs on lines inside of an open block comment.
+    block_content: Option<Regex>,
+    /// Matches comment lines that can continue a TODO.
+    continuation: Regex,
+    is_block: bool,
 }
 
-/// Parses content and creates a list of TODOs found in content
-pub fn parse_content<B>(content_buf: &mut B, regexs: &[Regex]) -> Result<Vec<Todo>, std::io::Error>
-where
-    B: BufRead,
-{
-    trace!("capturing content against {} regexs", regexs.len());
+impl CommentRegex {
+    fn new(comment_type: &CommentType, tags: &[String]) -> CommentRegex {
+        let block_content = comment_type
+            .suffix()
+            .map(|_| get_regex_for_block_content(tags, comment_type).unwrap());
 
-    let mut todos = Vec::new();
-    for (line_num, line_result) in content_buf.lines().enumerate() {
-        let line = line_result?;
-
-        for re in regexs.iter() {
-            if let Some(todo_caps) = re.captures(&line) {
-                let content: Cow<str> = match todo_caps.get(2) {
-                    Some(user) => Cow::Owned(format!(
-                        "@{} {}",
-                        user.as_str(),
-                        todo_caps.get(3).unwrap().as_str()
-                    )),
-                    None => Cow::Borrowed(&todo_caps.get(3).map_or_else(|| "", |s| s.as_str())),
-                };
-
-                let todo = Todo::new(line_num + 1, &todo_caps[1], content);
-                todos.push(todo);
-            };
+        let in_block = comment_type.suffix().is_some();
+
+        CommentRegex {
+            line: get_regex_for_comment(tags, comment_type).unwrap(),
+            block_content,
+            continuation: get_regex_for_continuation(comment_type, in_block).unwrap(),
+            is_block: in_block,
         }
     }
+}
 
-    Ok(todos)
+/// Regexs for each comment type along with the lexer for the content type.
+#[derive(Debug, Clone)]
+pub struct ParserRegexs {
+    comments: Vec<CommentRegex>,
+    lexer: Lexer,
+    /// Literal search for the tags that is used to skip text that cannot hold a TODO.
+    /// It is `None` if some tag cannot be searched for literally.
+    tag_finder: Option<AhoCorasick>,
+}
+
+impl ParserRegexs {
+    /// Returns the lexer for the content type.
+    pub fn lexer(&self) -> &Lexer {
+        &self.lexer
+    }
+
+    /// Returns false only if `text` cannot hold a TODO.
+    /// This is much faster than running the capture regexs on `text`.
+    fn may_have_todo(&self, text: &str) -> bool {
+        match &self.tag_finder {
+            Some(tag_finder) => tag_finder.is_match(text),
+            None => true,
+        }
+    }
+}
+
+/// Builds Regexs for use with parse_content.
+pub fn build_parser_regexs(comment_types: &CommentTypes, tags: &[String]) -> ParserRegexs {
+    // tags are put into the regexs as is, so only plain ASCII words can be searched for literally
+    let tag_finder = if tags.iter().all(|t| t.is_ascii() && escape(t) == *t) {
+        Some(
+            AhoCorasickBuilder::new()
+                .ascii_case_insensitive(true)
+                .build(tags)
+                .unwrap(),
+        )
+    } else {
+        None
+    };
+
+    ParserRegexs {
+        comments: comment_types
+            .iter()
+            .map(|c| CommentRegex::new(c, tags))
+            .collect(),
+        lexer: Lexer::new(comment_types),
+        tag_finder,
+    }
+}
+
+/// Parses content and creates a list of TODOs found in content.
+///
+/// If `trailing_comments` is true, TODOs in comments that come after code on the same line are
+/// also found.
+pub fn parse_content<B>(
+    content_buf: &mut B,
+    regexs: &ParserRegexs,
+    trailing_comments: bool,
+) -> Result<Vec<Todo>, std::io::Error>
+where
+    B: BufRead,
+{
+    parse_content_with_filter(content_buf, regexs, trailing_comments, |_| true)
 }
 
 /// Parses content and creates a list of TODOs found in content. Only adds TODOs that satisfy pred.
 pub fn parse_content_with_filter<P>(
     content_buf: &mut impl BufRead,
-    regexs: &[Regex],
+    regexs: &ParserRegexs,
+    trailing_comments: bool,
     pred: P,
 ) -> Result<Vec<Todo>, std::io::Error>
 where
     P: Fn(&Todo) -> bool,
 {
-    trace!("capturing content against {} regexs", regexs.len());
+    trace!("capturing content against {} regexs", regexs.comments.len());
 
     let mut todos = Vec::new();
-    for (line_num, line_result) in content_buf.lines().enumerate() {
-        let line = line_result?;
-
-        for re in regexs.iter() {
-            if let Some(todo_caps) = re.captures(&line) {
-                let content: Cow<str> = match todo_caps.get(2) {
-                    Some(user) => Cow::Owned(format!(
-                        "@{} {}",
-                        user.as_str(),
-                        todo_caps.get(3).unwrap().as_str()
-                    )),
-                    None => Cow::Borrowed(&todo_caps.get(3).map_or_else(|| "", |s| s.as_str())),
-                };
-
-                let todo = Todo::new(line_num + 1, &todo_caps[1], content);
-                if pred(&todo) {
-                    todos.push(todo);
+    // duplicates are counted before pred so filtering does not change TODO IDs
+    let mut counter = OccurrenceCounter::default();
+    // state of the lexer at the start of the line
+    let mut state = LexState::Code;
+    // TODO that may still be continued on the following lines
+    let mut pending: Option<PendingTodo> = None;
+    // reuse the same buffer for every line to avoid allocating
+    let mut line_buf = String::new();
+    let mut line_num = 0;
+    while content_buf.read_line(&mut line_buf)? > 0 {
+        let line = line_buf.trim_end_matches(&['\n', '\r'][..]);
+        line_num += 1;
+
+        let (spans, end_state) = regexs.lexer.lex_line(line, state);
+
+        if let LexState::BlockComment(i) = state {
+            let comment = &regexs.comments[i];
+            let todo_caps = if regexs.may_have_todo(line) {
+                comment.block_content.as_ref().unwrap().captures(line)
+            } else {
+                None
+            };
+
+            if let Some(todo_caps) = todo_caps {
+                push_pending(&mut todos, pending.take(), &mut counter, &pred);
+                pending = Some(PendingTodo::new(line_num, i, 0, &todo_caps));
+            } else if !continue_pending(&mut pending, line_num, i, comment, line) {
+                push_pending(&mut todos, pending.take(), &mut counter, &pred);
+            }
+        }
+
+        let mut found_todo = false;
+        for span in spans.iter().filter(|span| !span.continued) {
+            let i = match span.kind {
+                SpanKind::Comment(i) => i,
+                _ => continue,
+            };
+
+            // comments that come after code are only searched for in trailing comment mode
+            if !trailing_comments && !line[..span.start].trim().is_empty() {
+                continue;
+            }
+
+            if !regexs.may_have_todo(&line[span.start..span.end]) {
+                continue;
+            }
+
+            if let Some(todo_caps) = regexs.comments[i].line.captures(&line[span.start..]) {
+                push_pending(&mut todos, pending.take(), &mut counter, &pred);
+                pending = Some(PendingTodo::new(line_num, i, span.start, &todo_caps));
+                found_todo = true;
+            }
+        }
+
+        if !found_todo {
+            let continued = match (&pending, state) {
+                // lines in block comments were already checked for continuations
+                (_, LexState::BlockComment(_)) => true,
+                // only single-line comments continue on lines outside of blocks
+                (Some(p), LexState::Code) if !regexs.comments[p.comment_index].is_block => {
+                    let i = p.comment_index;
+                    continue_pending(&mut pending, line_num, i, &regexs.comments[i], line)
                 }
+                _ => false,
             };
+
+            if !continued {
+                push_pending(&mut todos, pending.take(), &mut counter, &pred);
+            }
         }
+
+        state = end_state;
+        line_buf.clear();
     }
 
+    push_pending(&mut todos, pending, &mut counter, &pred);
+
     Ok(todos)
 }
 
+/// A TODO that has been found but that may continue on the following comment lines.
+struct PendingTodo {
+    todo: Todo,
+    /// index of the comment type the TODO is in
+    comment_index: usize,
+    /// column where the TODO tag starts
+    tag_column: usize,
+}
+
+impl PendingTodo {
+    /// Creates PendingTodo from captures of the line starting at the column `offset`.
+    fn new(line: usize, comment_index: usize, offset: usize, todo_caps: &Captures) -> PendingTodo {
+        PendingTodo {
+            todo: todo_from_captures(line, todo_caps),
+            comment_index,
+            tag_column: offset + todo_caps.get(1).unwrap().start(),
+        }
+    }
+}
+
+/// Appends `line` to the pending TODO if it is a continuation of it.
+/// Continuation lines are comments of the same type that are indented past the TODO tag.
+///
+/// Returns true if `line` continued the pending TODO.
+fn continue_pending(
+    pending: &mut Option<PendingTodo>,
+    line_num: usize,
+    comment_index: usize,
+    comment: &CommentRegex,
+    line: &str,
+) -> bool {
+    let p = match pending {
+        Some(p) if p.comment_index == comment_index => p,
+        _ => return false,
+    };
+
+    match comment
+        .continuation
+        .captures(line)
+        .and_then(|caps| caps.get(1))
+    {
+        Some(text) if !text.as_str().is_empty() && text.start() > p.tag_column => {
+            p.todo.add_continuation(line_num, text.as_str());
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Adds the pending TODO to todos if it satisfies pred.
+fn push_pending<P>(
+    todos: &mut Vec<Todo>,
+    pending: Option<PendingTodo>,
+    counter: &mut OccurrenceCounter,
+    pred: &P,
+) where
+    P: Fn(&Todo) -> bool,
+{
+    if let Some(mut p) = pending {
+        counter.count(&mut p.todo);
+        if pred(&p.todo) {
+            todos.push(p.todo);
+        }
+    }
+}
+
+/// Creates Todo from the captures of a Regex built by `custom_tags`.
+fn todo_from_captures(line: usize, todo_caps: &Captures) -> Todo {
+    let content: Cow<str> = match todo_caps.get(2) {
+        Some(user) => Cow::Owned(format!(
+            "@{} {}",
+            user.as_str(),
+            todo_caps.get(3).unwrap().as_str()
+        )),
+        None => Cow::Borrowed(todo_caps.get(3).map_or_else(|| "", |s| s.as_str())),
+    };
+
+    Todo::new(line, &todo_caps[1], content)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::io::Cursor;
 
-    use crate::comments::CommentTypes;
+    use crate::comments::{CommentTypes, StringType};
 
     fn test_content(content: &str, exp_result: Option<&str>, file_ext: &str) {
         let comment_types = match file_ext {
@@ -106,6 +302,7 @@ mod tests {
         let todos = parse_content(
             &mut content_buf,
             &build_parser_regexs(&comment_types, &["TODO".to_string()]),
+            false,
         )
         .unwrap();
 
@@ -130,6 +327,7 @@ mod tests {
         let todos = parse_content(
             &mut content_buf,
             &build_parser_regexs(&comment_types, &["TODO".to_string()]),
+            false,
         )
         .unwrap();
 
@@ -144,6 +342,43 @@ mod tests {
         }
     }
 
+    fn test_todo_lines(content: &str, exp_todos: &[(usize, &str)], file_ext: &str) {
+        test_todo_lines_with_trailing(content, exp_todos, file_ext, false);
+    }
+
+    fn test_todo_lines_with_trailing(
+        content: &str,
+        exp_todos: &[(usize, &str)],
+        file_ext: &str,
+        trailing_comments: bool,
+    ) {
+        let comment_types = match file_ext {
+            "py" => CommentTypes::new()
+                .add_single("#")
+                .add_block("\"\"\"", "\"\"\"")
+                .add_string(StringType::new_quoted("\""))
+                .add_string(StringType::new_quoted("'")),
+            _ => CommentTypes::new()
+                .add_single("//")
+                .add_block("/*", "*/")
+                .add_string(StringType::new_multiline_quoted("\""))
+                .add_string(StringType::new_char("'"))
+                .add_string(StringType::new_raw("r")),
+        };
+
+        let mut content_buf = Cursor::new(content);
+        let todos = parse_content(
+            &mut content_buf,
+            &build_parser_regexs(&comment_types, &["TODO".to_string()]),
+            trailing_comments,
+        )
+        .unwrap();
+
+        let found_todos: Vec<(usize, &str)> =
+            todos.iter().map(|t| (t.line, t.content.as_str())).collect();
+        assert_eq!(exp_todos, found_todos.as_slice());
+    }
+
     #[test]
     fn find_todos_block_and_line1() {
         test_content("/* // todo: item */", None, "rs");
@@ -193,4 +428,218 @@ mod tests {
             "c",
         );
     }
+
+    #[test]
+    fn find_todos_multiline_block() {
+        test_todo_lines(
+            "/*\n * TODO: item1\n * todo: item2 */\n// TODO: item3\n",
+            &[(2, "item1"), (3, "item2"), (4, "item3")],
+            "rs",
+        );
+    }
+
+    #[test]
+    fn find_todos_multiline_block_first_line() {
+        test_todo_lines("/* TODO: item\n  more\n*/", &[(1, "item")], "rs");
+    }
+
+    #[test]
+    fn find_todos_multiline_block_closed() {
+        test_todo_lines("/* comment */ code();\nTODO: not a comment\n", &[], "rs");
+    }
+
+    #[test]
+    fn find_todos_multiline_block_in_line_comment() {
+        test_todo_lines("// comment /*\n  TODO: not a comment\n", &[], "rs");
+    }
+
+    #[test]
+    fn find_todos_multiline_block_after_code() {
+        test_todo_lines(
+            "code(); /* comment\n  TODO: item */ code();\n  TODO: not a comment\n",
+            &[(2, "item")],
+            "rs",
+        );
+    }
+
+    #[test]
+    fn find_todos_multiline_docstring() {
+        test_todo_lines(
+            "def f():\n    \"\"\"Docstring\n    todo: item\n    \"\"\"\n    todo: not a comment\n",
+            &[(3, "item")],
+            "py",
+        );
+    }
+
+    #[test]
+    fn find_todos_continuation() {
+        test_todo_lines(
+            "// TODO: refactor this\n//   because the cache is shared\n// not part of it\n",
+            &[(1, "refactor this because the cache is shared")],
+            "rs",
+        );
+    }
+
+    #[test]
+    fn find_todos_continuation_not_indented() {
+        test_todo_lines(
+            "  // TODO: item\n  // comment\n  //\n  //   comment\n",
+            &[(1, "item")],
+            "rs",
+        );
+    }
+
+    #[test]
+    fn find_todos_continuation_new_todo() {
+        test_todo_lines(
+            "// TODO: item1\n//    TODO: item2\n//      more\n",
+            &[(1, "item1"), (2, "item2 more")],
+            "rs",
+        );
+    }
+
+    #[test]
+    fn find_todos_continuation_other_comment_type() {
+        test_todo_lines("/* TODO: item1 */\n//    more\n", &[(1, "item1")], "rs");
+    }
+
+    #[test]
+    fn find_todos_continuation_block() {
+        test_todo_lines(
+            "/*\n * TODO: item\n *   more\n *   and more */\n *   not a comment\n",
+            &[(2, "item more and more")],
+            "rs",
+        );
+    }
+
+    #[test]
+    fn find_todos_continuation_end_line() {
+        let comment_types = CommentTypes::new().add_single("//");
+        let mut content_buf = Cursor::new("// TODO(u1): item\n//   @u2 more\n//   more\ncode();");
+        let todos = parse_content_with_filter(
+            &mut content_buf,
+            &build_parser_regexs(&comment_types, &["TODO".to_string()]),
+            false,
+            |t| t.tags_user("u2"),
+        )
+        .unwrap();
+
+        assert_eq!(1, todos.len());
+        assert_eq!(1, todos[0].line);
+        assert_eq!(3, todos[0].end_line);
+        assert_eq!("@u1 item @u2 more more", todos[0].content);
+    }
+
+    #[test]
+    fn find_todos_trailing_off() {
+        test_todo_lines("let x = foo(); // TODO: handle error", &[], "rs");
+    }
+
+    #[test]
+    fn find_todos_trailing() {
+        test_todo_lines_with_trailing(
+            "let x = foo(); // TODO: handle error\nfoo(); /* todo: item */ bar();",
+            &[(1, "handle error"), (2, "item")],
+            "rs",
+            true,
+        );
+    }
+
+    #[test]
+    fn find_todos_trailing_in_string() {
+        test_todo_lines_with_trailing(
+            "let s = \"// TODO: not a comment\";\nlet s = \"\\\" // TODO: escaped\";",
+            &[],
+            "rs",
+            true,
+        );
+    }
+
+    #[test]
+    fn find_todos_trailing_after_string() {
+        test_todo_lines_with_trailing(
+            "let s = \"http://a.b\"; // TODO: item\nfn f<'a>(s: &'a str) {} // TODO: item2",
+            &[(1, "item"), (2, "item2")],
+            "rs",
+            true,
+        );
+    }
+
+    #[test]
+    fn find_todos_trailing_after_block() {
+        test_todo_lines_with_trailing(
+            "/* comment\n end */ foo(); // TODO: item\n  // more\n",
+            &[(2, "item")],
+            "rs",
+            true,
+        );
+    }
+
+    #[test]
+    fn find_todos_trailing_py() {
+        test_todo_lines_with_trailing(
+            "a = '# todo: not a comment'\nb = 1 # todo: item",
+            &[(2, "item")],
+            "py",
+            true,
+        );
+    }
+
+    #[test]
+    fn find_todos_multiline_string() {
+        test_todo_lines_with_trailing(
+            "let s = \"start\n// TODO: not a comment\nend\"; // TODO: item\n// TODO: item2",
+            &[(3, "item"), (4, "item2")],
+            "rs",
+            true,
+        );
+    }
+
+    #[test]
+    fn find_todos_raw_string() {
+        test_todo_lines_with_trailing(
+            "let s = r#\"\" // TODO: not a comment\"#; // TODO: item",
+            &[(1, "item")],
+            "rs",
+            true,
+        );
+    }
+
+    #[test]
+    fn find_todos_no_string_types() {
+        let comment_types = CommentTypes::new().add_single("//");
+        let mut content_buf = Cursor::new("let s = \"// TODO: item\";");
+        let todos = parse_content(
+            &mut content_buf,
+            &build_parser_regexs(&comment_types, &["TODO".to_string()]),
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(1, todos.len());
+    }
+
+    #[test]
+    fn find_todos_tag_prefilter() {
+        let comment_types = CommentTypes::new().add_single("//").add_block("/*", "*/");
+        let regexs = build_parser_regexs(&comment_types, &["TODO".to_string()]);
+        assert!(regexs.tag_finder.is_some());
+        assert!(regexs.may_have_todo("// tOdO: item"));
+        assert!(!regexs.may_have_todo("// item"));
+
+        let mut content_buf = Cursor::new("/*\n todo: item1\n*/\n// tOdO: item2");
+        let todos = parse_content(&mut content_buf, &regexs, false).unwrap();
+        assert_eq!(2, todos.len());
+    }
+
+    #[test]
+    fn find_todos_regex_tag_no_prefilter() {
+        let comment_types = CommentTypes::new().add_single("//");
+        let regexs = build_parser_regexs(&comment_types, &["TO+DO".to_string()]);
+        assert!(regexs.tag_finder.is_none());
+
+        let mut content_buf = Cursor::new("// tooodo: item");
+        let todos = parse_content(&mut content_buf, &regexs, false).unwrap();
+        assert_eq!(1, todos.len());
+    }
 }
@@ -6,9 +6,14 @@ use std::fs::{rename, File};
 use std::io::{BufRead, BufReader, BufWriter, Write};
 
 use crate::errors::TodoRError;
-use crate::todo::TodoFile;
-
-pub fn remove_todo_by_index(todo_file: &mut TodoFile, ind: usize) -> Result<(), Error> {
+use crate::lexer::{LexState, Lexer, Span, SpanKind};
+use crate::todo::{Todo, TodoFile};
+
+pub fn remove_todo_by_index(
+    todo_file: &mut TodoFile,
+    ind: usize,
+    lexer: &Lexer,
+) -> Result<(), Error> {
     assert!(ind < todo_file.todos.len());
 
     let old_file = File::open(&todo_file.filepath)?;
@@ -18,18 +23,28 @@ pub fn remove_todo_by_index(todo_file: &mut TodoFile, ind: usize) -> Result<(),
     let mut file_reader = BufReader::new(old_file);
     let mut file_writer = BufWriter::new(temp_file);
 
-    let todo_line = todo_file.todos.remove(ind).line;
+    let todo = todo_file.todos.remove(ind);
 
     debug!(
-        "removing content in `{}` on line {}",
+        "removing content in `{}` on lines {}-{}",
         todo_file.filepath.display(),
-        todo_line
+        todo.line,
+        todo.end_line
     );
-    copy_except_line(&mut file_reader, &mut file_writer, todo_line)?;
-
-    for todo in &mut todo_file.todos[ind..] {
-        todo.line -= 1;
+    let num_lines = copy_except_lines(
+        &mut file_reader,
+        &mut file_writer,
+        todo.line,
+        todo.end_line,
+        &todo.tag,
+        lexer,
+    )?;
+
+    for later_todo in &mut todo_file.todos[ind..] {
+        later_todo.line -= num_lines;
+        later_todo.end_line -= num_lines;
     }
+    renumber_duplicates(&mut todo_file.todos[ind..], &todo);
 
     // replace old file with temp file
     rename(temp_filepath, &todo_file.filepath)?;
@@ -37,7 +52,11 @@ pub fn remove_todo_by_index(todo_file: &mut TodoFile, ind: usize) -> Result<(),
     Ok(())
 }
 
-pub fn remove_todo_by_line(todo_file: &mut TodoFile, line: usize) -> Result<(), Error> {
+pub fn remove_todo_by_line(
+    todo_file: &mut TodoFile,
+    line: usize,
+    lexer: &Lexer,
+) -> Result<(), Error> {
     let del_index: usize;
     // Kind of annoyed there is no retain_mut() to make this easier...
     {
@@ -58,6 +77,8 @@ pub fn remove_todo_by_line(todo_file: &mut TodoFile, line: usize) -> Result<(),
         }
 
         del_index = i;
+        let end_line = todo_to_check.end_line;
+        let tag = todo_to_check.tag.clone();
 
         let old_file = File::open(&todo_file.filepath)?;
         let temp_filepath = todo_file.filepath.with_extension("tmp");
@@ -67,54 +88,179 @@ pub fn remove_todo_by_line(todo_file: &mut TodoFile, line: usize) -> Result<(),
         let mut file_writer = BufWriter::new(temp_file);
 
         debug!(
-            "removing content in `{}` on line {}",
+            "removing content in `{}` on lines {}-{}",
             todo_file.filepath.display(),
-            line
+            line,
+            end_line
         );
-        copy_except_line(&mut file_reader, &mut file_writer, line)?;
+        let num_lines = copy_except_lines(
+            &mut file_reader,
+            &mut file_writer,
+            line,
+            end_line,
+            &tag,
+            lexer,
+        )?;
 
         // replace old file with temp file
         rename(temp_filepath, &todo_file.filepath)?;
 
         for (_, todo) in todos {
-            todo.line -= 1;
+            todo.line -= num_lines;
+            todo.end_line -= num_lines;
         }
     }
 
-    todo_file.todos.remove(del_index);
+    let todo = todo_file.todos.remove(del_index);
+    renumber_duplicates(&mut todo_file.todos[del_index..], &todo);
 
     Ok(())
 }
 
-fn copy_except_line<B, W>(orig: &mut B, copy: &mut W, line_number: usize) -> Result<(), Error>
+/// Moves duplicates of a removed TODO up by one so their IDs match a new search of the file.
+fn renumber_duplicates(later_todos: &mut [Todo], removed: &Todo) {
+    let content = removed.normalized_content();
+    for todo in later_todos {
+        if todo.tag == removed.tag && todo.normalized_content() == content {
+            todo.occurrence -= 1;
+        }
+    }
+}
+
+/// Copies orig into copy skipping the lines from `first_line` to `last_line` inclusive.
+///
+/// If the last skipped line closes the block comment the TODO is in, the end of the comment and
+/// any code after it are kept on a line so the rest of the file is not commented out.
+/// If the TODO with tag trails code, only the comment is removed from the first line.
+///
+/// Returns the number of lines removed, not counting a line that is partly kept.
+fn copy_except_lines<B, W>(
+    orig: &mut B,
+    copy: &mut W,
+    first_line: usize,
+    last_line: usize,
+    tag: &str,
+    lexer: &Lexer,
+) -> Result<usize, Error>
 where
     B: BufRead,
     W: Write,
 {
-    let orig_lines = orig.lines();
-    let mut line_skip_iter = orig_lines
-        .enumerate()
-        .filter(|&(i, _)| i != line_number - 1)
-        .map(|(_, l)| l);
-
-    // First line needs no '\n' char
-    {
-        let first_line = match line_skip_iter.next() {
-            Some(first_line) => first_line?,
-            None => return Ok(()), // Input is empty
+    let mut num_removed = 0;
+    // state of the lexer at the start of the line
+    let mut state = LexState::Code;
+    let mut opened_before = false;
+    let mut first_indent = String::new();
+    let mut wrote_line = false;
+    for (i, line) in orig.lines().enumerate() {
+        let line = line?;
+        let line_num = i + 1;
+
+        let kept = if line_num < first_line || line_num > last_line {
+            state = lexer.lex_line(&line, state).1;
+            Some(line)
+        } else {
+            let (spans, end_state) = lexer.lex_line(&line, state);
+            let span = if line_num == first_line {
+                opened_before = matches!(state, LexState::BlockComment(_));
+                first_indent = line[..line.len() - line.trim_start().len()].to_string();
+                todo_span(&line, &spans, tag)
+            } else {
+                spans
+                    .iter()
+                    .find(|span| matches!(span.kind, SpanKind::Comment(_)))
+            };
+            state = end_state;
+
+            let mut kept = if line_num == last_line {
+                kept_comment_end(&line, span, end_state, lexer, opened_before, &first_indent)
+            } else {
+                None
+            };
+            // code before a trailing TODO is kept along with anything after its comment
+            let code_before = match span {
+                Some(span) if line_num == first_line && !opened_before => {
+                    line[..span.start].trim_end()
+                }
+                _ => "",
+            };
+            if !code_before.trim().is_empty() {
+                kept = Some(match kept {
+                    Some(rest) => format!("{} {}", code_before, rest.trim_start()),
+                    None => code_before.to_string(),
+                });
+            }
+            // lines that are partly kept do not move the lines after them
+            if kept.is_none() {
+                num_removed += 1;
+            }
+            kept
         };
 
-        copy.write_all(&first_line.into_bytes())?;
+        if let Some(kept) = kept {
+            // First line needs no '\n' char
+            if wrote_line {
+                copy.write_all(b"\n")?;
+            }
+            copy.write_all(kept.as_bytes())?;
+            wrote_line = true;
+        }
+    }
+
+    Ok(num_removed)
+}
+
+/// Gets the part of the last line of a removed TODO that has to be kept.
+///
+/// If the block comment was opened before the TODO, its closer is kept along with any code after
+/// it. If the comment was opened by the TODO, only code after the closer is kept and it is
+/// indented like the first line of the TODO.
+fn kept_comment_end(
+    line: &str,
+    span: Option<&Span>,
+    end_state: LexState,
+    lexer: &Lexer,
+    opened_before: bool,
+    first_indent: &str,
+) -> Option<String> {
+    let span = span?;
+    let comment_index = match span.kind {
+        SpanKind::Comment(i) => i,
+        _ => unreachable!(),
+    };
+    // single-line comments run until the end of the line
+    let suffix = lexer.suffix(comment_index)?;
+    if span.end == line.len() && end_state == LexState::BlockComment(comment_index) {
+        // comment is still open
+        return None;
     }
 
-    // iterate skipping the line with the TODO
-    for line in line_skip_iter {
-        let l: String = line?;
-        copy.write_all(b"\n")?;
-        copy.write_all(&l.into_bytes())?;
+    let (indent, rest) = if opened_before {
+        (
+            &line[..line.len() - line.trim_start().len()],
+            &line[span.end - suffix.len()..],
+        )
+    } else {
+        (first_indent, line[span.end..].trim_start())
+    };
+
+    if rest.trim().is_empty() {
+        None
+    } else {
+        Some(format!("{}{}", indent, rest))
     }
+}
 
-    Ok(())
+/// Finds the comment in line that has the TODO tag.
+/// Falls back to the first comment if none of them has it.
+fn todo_span<'s>(line: &str, spans: &'s [Span], tag: &str) -> Option<&'s Span> {
+    let mut comments = spans
+        .iter()
+        .filter(|span| matches!(span.kind, SpanKind::Comment(_)));
+    let first = comments.clone().next();
+    comments
+        .find(|span| line[span.start..span.end].to_uppercase().contains(tag))
+        .or(first)
 }
 
 #[cfg(test)]
@@ -122,11 +268,25 @@ mod tests {
     use super::*;
     use std::io::Cursor;
 
+    use crate::comments::CommentTypes;
+
+    fn rs_lexer() -> Lexer {
+        Lexer::new(&CommentTypes::new().add_single("//").add_block("/*", "*/"))
+    }
+
     fn assert_copy(orig_text: &str, expected_out_text: &str, todo_line: usize) {
         let mut out_buf: Cursor<Vec<u8>> = Cursor::new(Vec::new());
         let mut in_buf = Cursor::new(orig_text);
 
-        copy_except_line(&mut in_buf, &mut out_buf, todo_line).unwrap();
+        copy_except_lines(
+            &mut in_buf,
+            &mut out_buf,
+            todo_line,
+            todo_line,
+            "TODO",
+            &rs_lexer(),
+        )
+        .unwrap();
 
         let out_bytes = out_buf.into_inner();
         assert_eq!(
@@ -211,4 +371,131 @@ other.stuff()
 
         assert_copy(orig_text, expected_out_text, todo_line);
     }
+
+    #[test]
+    fn test_remove_lines() {
+        let orig_text = "code.run()
+// item
+//   item continued
+other.stuff()";
+
+        let expected_out_text = "code.run()
+other.stuff()";
+
+        let mut out_buf: Cursor<Vec<u8>> = Cursor::new(Vec::new());
+        let mut in_buf = Cursor::new(orig_text);
+        copy_except_lines(&mut in_buf, &mut out_buf, 2, 3, "TODO", &rs_lexer()).unwrap();
+
+        assert_eq!(
+            expected_out_text.to_string(),
+            String::from_utf8(out_buf.into_inner()).unwrap()
+        );
+    }
+
+    fn assert_copy_lines(orig_text: &str, expected_out_text: &str, first: usize, last: usize) {
+        let mut out_buf: Cursor<Vec<u8>> = Cursor::new(Vec::new());
+        let mut in_buf = Cursor::new(orig_text);
+        copy_except_lines(&mut in_buf, &mut out_buf, first, last, "TODO", &rs_lexer()).unwrap();
+
+        assert_eq!(
+            expected_out_text.to_string(),
+            String::from_utf8(out_buf.into_inner()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_remove_lines_keeps_block_end() {
+        assert_copy_lines(
+            "/*\n * TODO: item\n *   more */\nfn x() {}",
+            "/*\n */\nfn x() {}",
+            2,
+            3,
+        );
+        assert_copy_lines(
+            "/*\n * TODO: item */ fn x() {}\nfn y() {}",
+            "/*\n */ fn x() {}\nfn y() {}",
+            2,
+            2,
+        );
+    }
+
+    #[test]
+    fn test_remove_block_keeps_code() {
+        assert_copy_lines(
+            "/* TODO: item\n *   more */ fn x() {}\nfn y() {}",
+            "fn x() {}\nfn y() {}",
+            1,
+            2,
+        );
+        assert_copy_lines("/* TODO: item\n *   more */\nfn y() {}", "fn y() {}", 1, 2);
+    }
+
+    #[test]
+    fn test_remove_trailing_todo_keeps_code() {
+        assert_copy_lines(
+            "let x = foo(); // TODO: handle error\nbar();",
+            "let x = foo();\nbar();",
+            1,
+            1,
+        );
+        assert_copy_lines(
+            "    foo(); /* a */ baz(); // TODO: x\nbar();",
+            "    foo(); /* a */ baz();\nbar();",
+            1,
+            1,
+        );
+    }
+
+    #[test]
+    fn test_remove_trailing_block_todo_keeps_code() {
+        assert_copy_lines("foo(); /* TODO: x */\nbar();", "foo();\nbar();", 1, 1);
+        assert_copy_lines(
+            "foo(); /* TODO: x */ baz();\nbar();",
+            "foo(); baz();\nbar();",
+            1,
+            1,
+        );
+        assert_copy_lines(
+            "foo(); /* TODO: x\n   more */ baz();\nbar();",
+            "foo();\nbaz();\nbar();",
+            1,
+            2,
+        );
+    }
+
+    #[test]
+    fn test_remove_todos_in_sequence() {
+        use crate::parser::{build_parser_regexs, parse_content};
+
+        let path = std::env::temp_dir().join(format!("todor-remover-{}.rs", std::process::id()));
+        std::fs::write(
+            &path,
+            "/*\n * TODO: one\n *   more */ fn x() {}\n// TODO: two\nfn y() {}\n",
+        )
+        .unwrap();
+        let comment_types = CommentTypes::new().add_single("//").add_block("/*", "*/");
+        let regexs = build_parser_regexs(&comment_types, &["TODO".to_string()]);
+        let mut todo_file = TodoFile::new(&path);
+        todo_file.set_todos(
+            parse_content(
+                &mut BufReader::new(File::open(&path).unwrap()),
+                &regexs,
+                false,
+            )
+            .unwrap(),
+        );
+        assert_eq!(todo_file.todos[1].line, 4);
+
+        remove_todo_by_index(&mut todo_file, 0, regexs.lexer()).unwrap();
+        assert_eq!(todo_file.todos[0].line, 3);
+        assert_eq!(todo_file.todos[0].end_line, 3);
+
+        remove_todo_by_index(&mut todo_file, 0, regexs.lexer()).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "/*\n */ fn x() {}\nfn y() {}"
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
 }
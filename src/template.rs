@@ -0,0 +1,388 @@
+// Module for rendering TODOs with user-defined templates
+
+use failure::Error;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::errors::TodoRError;
+use crate::format::{escape_csv, escape_tsv, escape_xml, Column};
+use crate::todo::PathedTodo;
+
+/// Filter that escapes or changes a field value, like `{text|json}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Filter {
+    Upper,
+    Lower,
+    Json,
+    Xml,
+    Csv,
+    Tsv,
+    Shell,
+}
+
+impl Filter {
+    fn from_name(name: &str) -> Result<Filter, Error> {
+        let filter = match name.trim() {
+            "upper" => Filter::Upper,
+            "lower" => Filter::Lower,
+            "json" => Filter::Json,
+            "xml" => Filter::Xml,
+            "csv" => Filter::Csv,
+            "tsv" => Filter::Tsv,
+            "shell" => Filter::Shell,
+            _ => {
+                return Err(invalid_template(format!(
+                    "'{}' is an invalid filter. Filters are upper, lower, json, xml, csv, tsv and shell",
+                    name.trim()
+                )))
+            }
+        };
+
+        Ok(filter)
+    }
+
+    fn apply(self, value: &str) -> String {
+        match self {
+            Filter::Upper => value.to_uppercase(),
+            Filter::Lower => value.to_lowercase(),
+            Filter::Json => {
+                // drop the quotes so the value can go inside a JSON string
+                let quoted = serde_json::to_string(value).unwrap();
+                quoted[1..quoted.len() - 1].to_string()
+            }
+            Filter::Xml => escape_xml(value),
+            Filter::Csv => escape_csv(value).into_owned(),
+            Filter::Tsv => escape_tsv(value).into_owned(),
+            Filter::Shell => format!("'{}'", value.replace('\'', r"'\''")),
+        }
+    }
+}
+
+/// Piece of a parsed template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Text(String),
+    Field {
+        name: String,
+        filters: Vec<Filter>,
+    },
+    /// Body that is only rendered if the field is not empty, or only if it is empty when inverted.
+    Section {
+        name: String,
+        inverted: bool,
+        body: Vec<Node>,
+    },
+}
+
+/// Part of a TemplateFormat, which sets the fields its Template can use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplatePart {
+    /// `todo`, which has the fields of a TODO
+    Todo,
+    /// `file_header` and `file_footer`, which have the fields `file` and `count`
+    File,
+    /// `header` and `footer`, which have the field `count`
+    Count,
+}
+
+impl TemplatePart {
+    /// Checks that name is a field of this part.
+    fn check_field(self, name: &str) -> Result<(), Error> {
+        let (is_field, part, fields) = match self {
+            TemplatePart::Todo => (
+                Column::from_name(name).is_ok(),
+                "todo",
+                "file, line, end_line, tag, text, users, id, author, author_email, commit and date",
+            ),
+            TemplatePart::File => (
+                name == "file" || name == "count",
+                "file_header and file_footer",
+                "file and count",
+            ),
+            TemplatePart::Count => (name == "count", "header and footer", "count"),
+        };
+
+        if is_field {
+            Ok(())
+        } else {
+            Err(invalid_template(format!(
+                "'{}' is an invalid field in {}. Fields are {}",
+                name, part, fields
+            )))
+        }
+    }
+}
+
+/// Template that renders fields of a TODO or file.
+///
+/// Fields are written as `{text}` and can be passed through filters like `{text|json}`.
+/// `{?users}...{/users}` is only rendered if the field is not empty and `{!users}...{/users}`
+/// only if it is. Use `{{` and `}}` for literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    /// Parses a Template for TODOs from a string like `{file}:{line}: [{tag}] {text}`.
+    pub fn parse(template: &str) -> Result<Template, Error> {
+        Template::parse_part(template, TemplatePart::Todo)
+    }
+
+    /// Parses a Template for part of a TemplateFormat.
+    /// Fields that part does not have are an error.
+    pub fn parse_part(template: &str, part: TemplatePart) -> Result<Template, Error> {
+        let nodes = parse_nodes(&mut template.chars().peekable(), part, None)?;
+        Ok(Template { nodes })
+    }
+
+    /// Renders the Template with fields looked up using value.
+    /// Fields that do not have a value are empty.
+    fn render(&self, value: &dyn Fn(&str) -> Option<String>) -> String {
+        let mut out = String::new();
+        render_nodes(&self.nodes, value, &mut out);
+        out
+    }
+}
+
+/// Templates used to write TODOs with the `template` format.
+///
+/// `todo` is rendered for every TODO. `file_header` and `file_footer` are rendered around the
+/// TODOs of each file and have the fields `file` and `count`. `header` and `footer` are rendered
+/// once around everything and have the field `count`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateFormat {
+    pub todo: Template,
+    pub header: Option<Template>,
+    pub footer: Option<Template>,
+    pub file_header: Option<Template>,
+    pub file_footer: Option<Template>,
+}
+
+impl TemplateFormat {
+    /// Creates a TemplateFormat that only renders todo for each TODO.
+    pub fn new(todo: Template) -> TemplateFormat {
+        TemplateFormat {
+            todo,
+            header: None,
+            footer: None,
+            file_header: None,
+            file_footer: None,
+        }
+    }
+
+    pub(crate) fn render_todo(&self, ptodo: &PathedTodo) -> String {
+        self.todo
+            .render(&|name| Column::from_name(name).ok().map(|c| c.value(ptodo)))
+    }
+
+    pub(crate) fn render_file_header(&self, file: &str, count: usize) -> Option<String> {
+        render_file_part(self.file_header.as_ref(), file, count)
+    }
+
+    pub(crate) fn render_file_footer(&self, file: &str, count: usize) -> Option<String> {
+        render_file_part(self.file_footer.as_ref(), file, count)
+    }
+
+    pub(crate) fn render_header(&self, count: usize) -> Option<String> {
+        render_count_part(self.header.as_ref(), count)
+    }
+
+    pub(crate) fn render_footer(&self, count: usize) -> Option<String> {
+        render_count_part(self.footer.as_ref(), count)
+    }
+}
+
+fn render_file_part(template: Option<&Template>, file: &str, count: usize) -> Option<String> {
+    template.map(|t| {
+        t.render(&|name| match name {
+            "file" => Some(file.to_string()),
+            "count" => Some(count.to_string()),
+            _ => None,
+        })
+    })
+}
+
+fn render_count_part(template: Option<&Template>, count: usize) -> Option<String> {
+    template.map(|t| {
+        t.render(&|name| match name {
+            "count" => Some(count.to_string()),
+            _ => None,
+        })
+    })
+}
+
+fn invalid_template(message: String) -> Error {
+    TodoRError::InvalidTemplate { message }.into()
+}
+
+/// Parses nodes until the end of the template or the end of the section named closing.
+fn parse_nodes(
+    chars: &mut Peekable<Chars>,
+    part: TemplatePart,
+    closing: Option<&str>,
+) -> Result<Vec<Node>, Error> {
+    let mut nodes = Vec::new();
+    let mut text = String::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '}' => return Err(invalid_template("'}' without a matching '{'".to_string())),
+            '{' => {
+                let mut tag = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => tag.push(c),
+                        None => return Err(invalid_template(format!("'{{{}' is not closed", tag))),
+                    }
+                }
+
+                if !text.is_empty() {
+                    nodes.push(Node::Text(std::mem::take(&mut text)));
+                }
+
+                let tag = tag.trim();
+                if let Some(name) = tag.strip_prefix('/') {
+                    let name = name.trim();
+                    if closing == Some(name) {
+                        return Ok(nodes);
+                    }
+                    return Err(invalid_template(format!(
+                        "'{{/{}}}' does not close a section",
+                        name
+                    )));
+                } else if tag.starts_with('?') || tag.starts_with('!') {
+                    let name = tag[1..].trim();
+                    part.check_field(name)?;
+                    let body = parse_nodes(chars, part, Some(name))?;
+                    nodes.push(Node::Section {
+                        name: name.to_string(),
+                        inverted: tag.starts_with('!'),
+                        body,
+                    });
+                } else {
+                    let mut parts = tag.split('|');
+                    let name = parts.next().unwrap_or("").trim();
+                    part.check_field(name)?;
+                    let filters = parts.map(Filter::from_name).collect::<Result<_, _>>()?;
+                    nodes.push(Node::Field {
+                        name: name.to_string(),
+                        filters,
+                    });
+                }
+            }
+            c => text.push(c),
+        }
+    }
+
+    if let Some(name) = closing {
+        return Err(invalid_template(format!(
+            "section '{}' is not closed with '{{/{}}}'",
+            name, name
+        )));
+    }
+
+    if !text.is_empty() {
+        nodes.push(Node::Text(text));
+    }
+
+    Ok(nodes)
+}
+
+fn render_nodes(nodes: &[Node], value: &dyn Fn(&str) -> Option<String>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Field { name, filters } => {
+                let field = value(name).unwrap_or_default();
+                let field = filters
+                    .iter()
+                    .fold(field, |field, filter| filter.apply(&field));
+                out.push_str(&field);
+            }
+            Node::Section {
+                name,
+                inverted,
+                body,
+            } => {
+                let is_set = value(name).is_some_and(|v| !v.is_empty());
+                if is_set != *inverted {
+                    render_nodes(body, value, out);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, fields: &[(&str, &str)]) -> String {
+        Template::parse(template).unwrap().render(&|name| {
+            fields
+                .iter()
+                .find(|(field, _)| *field == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn fields_and_filters() {
+        let fields = [
+            ("file", "src/a b.rs"),
+            ("line", "3"),
+            ("text", "say \"hi\""),
+        ];
+        assert_eq!(
+            render("{file}:{line}: {text}", &fields),
+            "src/a b.rs:3: say \"hi\""
+        );
+        assert_eq!(render("{{{ text | json }}}", &fields), "{say \\\"hi\\\"}");
+        assert_eq!(render("{text|upper|xml}", &fields), "SAY &quot;HI&quot;");
+        assert_eq!(render("vim {file|shell}", &fields), "vim 'src/a b.rs'");
+        assert_eq!(render("[{users}]", &fields), "[]");
+    }
+
+    #[test]
+    fn sections() {
+        let template = "{text}{?users} ({users}){/users}{!users} (unassigned){/users}";
+        assert_eq!(
+            render(template, &[("text", "fix"), ("users", "@ann")]),
+            "fix (@ann)"
+        );
+        assert_eq!(
+            render(template, &[("text", "fix"), ("users", "")]),
+            "fix (unassigned)"
+        );
+    }
+
+    #[test]
+    fn invalid_templates() {
+        assert!(Template::parse("{nope}").is_err());
+        assert!(Template::parse("{text|nope}").is_err());
+        assert!(Template::parse("{text").is_err());
+        assert!(Template::parse("text}").is_err());
+        assert!(Template::parse("{?users}open").is_err());
+        assert!(Template::parse("{?users}{/tag}").is_err());
+        assert!(Template::parse("{/users}").is_err());
+    }
+
+    #[test]
+    fn fields_of_parts() {
+        assert!(Template::parse("{count}").is_err());
+        assert!(Template::parse_part("{file} {count}", TemplatePart::File).is_ok());
+        assert!(Template::parse_part("{tag}", TemplatePart::File).is_err());
+        assert!(Template::parse_part("{count}", TemplatePart::Count).is_ok());
+        assert!(Template::parse_part("{?file}x{/file}", TemplatePart::Count).is_err());
+    }
+}
@@ -1,15 +1,20 @@
 // Module for holding Todo types.
 
 use failure::Error;
+use fnv::{FnvHashMap, FnvHasher};
 use lazy_static::lazy_static;
 use regex::Regex;
 use serde::ser::{SerializeSeq, SerializeStruct, Serializer};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
+use std::env::current_dir;
 use std::fmt;
+use std::hash::Hasher;
 use std::io::Write;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
+use crate::blame::blame_todo_file;
+use crate::cache::normalize_path;
 use crate::display::TodoRStyles;
 
 lazy_static! {
@@ -17,11 +22,30 @@ lazy_static! {
 }
 
 /// A struct holding the TODO and all the needed meta-information for it.
-#[derive(Debug, Clone)]
+///
+/// TODOs that continue over several comment lines start on `line` and end on `end_line`.
+/// `occurrence` counts the earlier TODOs in the same file with the same tag and content
+/// so that duplicate TODOs get different IDs.
+/// `blame` is only set when git blame information was asked for.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "TodoDeserial")]
 pub struct Todo {
     pub line: usize,
+    pub end_line: usize,
     pub tag: String,
     pub content: String,
+    pub occurrence: usize,
+    pub blame: Option<Blame>,
+}
+
+/// Who last changed the line a TODO starts on, as given by git blame.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Blame {
+    pub author: String,
+    pub author_email: String,
+    pub commit: String,
+    /// Author date in ISO 8601 format
+    pub date: String,
 }
 
 impl Todo {
@@ -29,9 +53,34 @@ impl Todo {
     pub fn new<'c>(line: usize, tag_str: &str, content: impl Into<Cow<'c, str>>) -> Todo {
         Todo {
             line,
+            end_line: line,
             tag: tag_str.to_uppercase(),
             content: content.into().into_owned(),
+            occurrence: 0,
+            blame: None,
+        }
+    }
+
+    /// Appends the text from a continuation line on `line` to the content of the TODO.
+    pub fn add_continuation(&mut self, line: usize, text: &str) {
+        if !self.content.is_empty() {
+            self.content.push(' ');
         }
+        self.content.push_str(text);
+        self.end_line = line;
+    }
+
+    /// Returns the number of lines the TODO spans.
+    pub fn num_lines(&self) -> usize {
+        self.end_line - self.line + 1
+    }
+
+    /// Returns the content with runs of whitespace collapsed into single spaces.
+    pub fn normalized_content(&self) -> String {
+        self.content
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
     }
 
     /// Returns ANSI colored output string
@@ -44,7 +93,7 @@ impl Todo {
 
         let tag_width = &self.tag.len().min(5);
         format!(
-            "  {}  {}{}  {}",
+            "  {}  {}{}  {}{}",
             // Columns align for up to 100,000 lines which should be fine
             styles
                 .line_number_style
@@ -54,6 +103,7 @@ impl Todo {
                 .paint(format!("{:w$}", &self.tag, w = tag_width)),
             format!("{:w$}", "", w = 5 - tag_width),
             styles.content_style.paint(content_out),
+            self.blame_string(styles),
         )
     }
 
@@ -61,6 +111,16 @@ impl Todo {
         &self,
         out_buffer: &mut impl Write,
         styles: &TodoRStyles,
+    ) -> Result<(), Error> {
+        self.write_style_string_with_id(out_buffer, styles, None)
+    }
+
+    /// Writes the ANSI colored output string followed by id if it is given.
+    pub(crate) fn write_style_string_with_id(
+        &self,
+        out_buffer: &mut impl Write,
+        styles: &TodoRStyles,
+        id: Option<&str>,
     ) -> Result<(), Error> {
         // Paint users using user_style by wrapping users with infix ansi-strings
         let cs_to_us = styles.content_style.infix(styles.user_style);
@@ -69,9 +129,13 @@ impl Todo {
         let content_out = USER_REGEX.replace_all(&self.content, paint_users);
 
         let tag_width = &self.tag.len().min(5);
+        let id_string = match id {
+            Some(id) => format!("  {}", styles.id_style.paint(format!("[{}]", id))),
+            None => String::new(),
+        };
         writeln!(
             out_buffer,
-            "  {}  {}{}  {}",
+            "  {}  {}{}  {}{}{}",
             // Columns align for up to 100,000 lines which should be fine
             styles
                 .line_number_style
@@ -81,11 +145,32 @@ impl Todo {
                 .paint(format!("{:w$}", &self.tag, w = tag_width)),
             format!("{:w$}", "", w = 5 - tag_width),
             styles.content_style.paint(content_out),
+            self.blame_string(styles),
+            id_string,
         )?;
 
         Ok(())
     }
 
+    /// Returns the author, date and short commit hash of the TODO painted with the blame style.
+    /// Returns an empty string if there is no blame information.
+    fn blame_string(&self, styles: &TodoRStyles) -> String {
+        match &self.blame {
+            Some(blame) => {
+                // only the day is shown to keep lines short
+                let day = blame.date.get(..10).unwrap_or(&blame.date);
+                let short_commit = blame.commit.get(..8).unwrap_or(&blame.commit);
+                format!(
+                    "  {}",
+                    styles
+                        .blame_style
+                        .paint(format!("({}, {}, {})", blame.author, day, short_commit))
+                )
+            }
+            None => String::new(),
+        }
+    }
+
     /// Returns all is tagged in the Todo.
     pub fn users(&self) -> Vec<&str> {
         USER_REGEX
@@ -111,6 +196,86 @@ impl Todo {
     }
 }
 
+/// Sets the `occurrence` of each TODO added so TODOs with the same tag and content are numbered
+/// in the order they are found in a file.
+#[derive(Debug, Default)]
+pub(crate) struct OccurrenceCounter {
+    seen: FnvHashMap<(String, String), usize>,
+}
+
+impl OccurrenceCounter {
+    pub fn count(&mut self, todo: &mut Todo) {
+        let count = self
+            .seen
+            .entry((todo.tag.clone(), todo.normalized_content()))
+            .or_insert(0);
+        todo.occurrence = *count;
+        *count += 1;
+    }
+}
+
+/// Numbers TODOs with the same tag and content in todos from the same file.
+pub(crate) fn count_occurrences(todos: &mut [Todo]) {
+    let mut counter = OccurrenceCounter::default();
+    for todo in todos {
+        counter.count(todo);
+    }
+}
+
+/// Returns the ID of todo in the file at filepath.
+///
+/// IDs are made from the path, tag, normalized content and occurrence of the TODO so they do not
+/// change when the TODO moves to a different line. If the workspace root is given, the path is
+/// taken relative to it so the same TODO gets the same ID wherever todor is run from.
+/// Otherwise the path is used as given.
+pub fn todo_id(filepath: &Path, todo: &Todo, root: Option<&Path>) -> String {
+    let mut hasher = FnvHasher::default();
+    match root {
+        Some(root) => {
+            let root = normalize_path(&current_dir().unwrap_or_default().join(root));
+            for part in root_relative_path(filepath, &root).split('/') {
+                hasher.write(part.as_bytes());
+                hasher.write(b"/");
+            }
+        }
+        None => {
+            for component in filepath.components() {
+                match component {
+                    Component::Normal(part) => hasher.write(part.to_string_lossy().as_bytes()),
+                    Component::ParentDir => hasher.write(b".."),
+                    _ => continue,
+                }
+                hasher.write(b"/");
+            }
+        }
+    }
+    for part in [&todo.tag, &todo.normalized_content()] {
+        hasher.write(part.as_bytes());
+        hasher.write_u8(0);
+    }
+    hasher.write_u64(todo.occurrence as u64);
+
+    // 48 bits are plenty to tell apart the TODOs in a workspace
+    format!("{:012x}", hasher.finish() >> 16)
+}
+
+/// Returns the path of filepath relative to abs_root using `/` as the separator.
+/// filepath is taken relative to the current directory if it is not absolute.
+pub(crate) fn root_relative_path(filepath: &Path, abs_root: &Path) -> String {
+    let curr_dir = current_dir().unwrap_or_default();
+    let abs_path = normalize_path(&curr_dir.join(filepath));
+    let rel_path = abs_path.strip_prefix(abs_root).unwrap_or(&abs_path);
+
+    rel_path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
 impl fmt::Display for Todo {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "line {}\t{}\t{}", self.line, self.tag, self.content,)
@@ -145,7 +310,24 @@ impl TodoFile {
         self.todos.len()
     }
 
-    /// Writes TODOs in a file serialized in the JSON format
+    /// Returns an iterator over the TODOs in the file whose IDs are made relative to root.
+    pub fn iter_in_root<'a>(&'a self, root: Option<&'a Path>) -> TodoFileIter<'a, TodoIter<'a>> {
+        TodoFileIter {
+            inner: self.todos.iter(),
+            file: &self.filepath,
+            root,
+        }
+    }
+
+    /// Attaches git blame information to every TODO in the file.
+    pub fn add_blame(&mut self) -> Result<(), Error> {
+        blame_todo_file(self)
+    }
+
+    /// Writes TODOs in a file serialized in the JSON format.
+    ///
+    /// IDs are left out since they are made relative to the workspace root, which a TodoFile
+    /// does not know. `TodoR` writes TODOs with their IDs.
     pub fn write_json(&self, out_buffer: &mut impl Write) -> Result<(), Error> {
         serde_json::to_writer(out_buffer, &self)?;
         Ok(())
@@ -157,26 +339,98 @@ impl Serialize for Todo {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("Todo", 4)?;
+        let mut state = serializer.serialize_struct("Todo", 6)?;
         state.serialize_field("line", &self.line)?;
+        // only multi-line TODOs list where they end
+        if self.end_line != self.line {
+            state.serialize_field("end_line", &self.end_line)?;
+        } else {
+            state.skip_field("end_line")?;
+        }
         state.serialize_field("tag", &self.tag)?;
         state.serialize_field("text", &self.content)?;
         state.serialize_field("users", &self.users())?;
+        // blame is only known when it was asked for
+        match &self.blame {
+            Some(blame) => state.serialize_field("blame", blame)?,
+            None => state.skip_field("blame")?,
+        }
         state.end()
     }
 }
 
+/// Todo as it is written by its `Serialize` implementation.
+/// Fields that are worked out from others like `users` are skipped.
+#[derive(Deserialize)]
+struct TodoDeserial {
+    line: usize,
+    end_line: Option<usize>,
+    tag: String,
+    text: String,
+    blame: Option<Blame>,
+}
+
+impl From<TodoDeserial> for Todo {
+    fn from(serial: TodoDeserial) -> Todo {
+        Todo {
+            line: serial.line,
+            end_line: serial.end_line.unwrap_or(serial.line),
+            tag: serial.tag,
+            content: serial.text,
+            occurrence: 0,
+            blame: serial.blame,
+        }
+    }
+}
+
 /// Helper struct for printing filename along with other TODO information.
-#[derive(Serialize)]
 pub struct PathedTodo<'a> {
     pub(crate) file: &'a Path,
-    #[serde(flatten)]
     pub(crate) todo: &'a Todo,
+    /// Workspace root that the ID is made relative to
+    pub(crate) root: Option<&'a Path>,
 }
 
 impl PathedTodo<'_> {
-    fn new<'a>(todo: &'a Todo, file: &'a Path) -> PathedTodo<'a> {
-        PathedTodo { file, todo }
+    fn new<'a>(todo: &'a Todo, file: &'a Path, root: Option<&'a Path>) -> PathedTodo<'a> {
+        PathedTodo { file, todo, root }
+    }
+
+    /// Returns the path of the file the TODO is in.
+    pub fn file(&self) -> &Path {
+        self.file
+    }
+
+    /// Returns the underlying TODO.
+    pub fn todo(&self) -> &Todo {
+        self.todo
+    }
+
+    /// Returns the stable ID of the TODO.
+    pub fn id(&self) -> String {
+        todo_id(self.file, self.todo, self.root)
+    }
+}
+
+impl Serialize for PathedTodo<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        struct PathedTodoSerial<'a> {
+            file: &'a Path,
+            id: String,
+            #[serde(flatten)]
+            todo: &'a Todo,
+        }
+
+        PathedTodoSerial {
+            file: self.file,
+            id: self.id(),
+            todo: self.todo,
+        }
+        .serialize(serializer)
     }
 }
 
@@ -188,6 +442,7 @@ where
 {
     inner: I,
     file: &'a Path,
+    root: Option<&'a Path>,
 }
 
 type TodoIter<'a> = std::slice::Iter<'a, Todo>;
@@ -196,6 +451,7 @@ impl<'a> From<&'a TodoFile> for TodoFileIter<'a, TodoIter<'a>> {
         TodoFileIter {
             inner: tf.todos.iter(),
             file: &tf.filepath,
+            root: None,
         }
     }
 }
@@ -207,7 +463,9 @@ where
     type Item = PathedTodo<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|t| PathedTodo::new(t, self.file))
+        self.inner
+            .next()
+            .map(|t| PathedTodo::new(t, self.file, self.root))
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
@@ -223,7 +481,7 @@ where
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner
             .next_back()
-            .map(|t| PathedTodo::new(t, self.file))
+            .map(|t| PathedTodo::new(t, self.file, self.root))
     }
 }
 
@@ -242,10 +500,20 @@ impl Serialize for TodoFile {
     where
         S: Serializer,
     {
+        // no IDs since the workspace root they are relative to is not known
+        #[derive(Serialize)]
+        struct FileTodoSerial<'a> {
+            file: &'a Path,
+            #[serde(flatten)]
+            todo: &'a Todo,
+        }
+
         let mut seq = serializer.serialize_seq(Some(self.len()))?;
         for todo in &self.todos {
-            let ptodo = PathedTodo::new(todo, &self.filepath);
-            seq.serialize_element(&ptodo)?;
+            seq.serialize_element(&FileTodoSerial {
+                file: &self.filepath,
+                todo,
+            })?;
         }
         seq.end()
     }
@@ -276,6 +544,78 @@ mod test {
         );
     }
 
+    #[test]
+    fn json_multiline_todo() {
+        let mut todo = Todo::new(2, "TODO", "item");
+        todo.add_continuation(3, "continued");
+
+        assert_eq!(
+            todo.to_json().unwrap(),
+            r#"{"line":2,"end_line":3,"tag":"TODO","text":"item continued","users":[]}"#,
+        );
+    }
+
+    #[test]
+    fn ids() {
+        let todo = Todo::new(2, "TODO", "fix  this");
+        let id = todo_id(Path::new("src/main.rs"), &todo, None);
+        assert_eq!(id.len(), 12);
+
+        // moving the TODO or changing whitespace keeps the ID
+        let moved = Todo::new(10, "TODO", "fix this");
+        assert_eq!(todo_id(Path::new("./src/main.rs"), &moved, None), id);
+
+        let mut duplicate = moved.clone();
+        duplicate.occurrence = 1;
+        assert_ne!(todo_id(Path::new("src/main.rs"), &duplicate, None), id);
+        assert_ne!(todo_id(Path::new("src/lib.rs"), &todo, None), id);
+        assert_ne!(
+            todo_id(
+                Path::new("src/main.rs"),
+                &Todo::new(2, "FIXME", "fix this"),
+                None
+            ),
+            id
+        );
+
+        // paths from the workspace root give the same ID from any directory
+        let root = current_dir().unwrap();
+        let in_root = todo_id(Path::new("src/main.rs"), &todo, Some(&root));
+        assert_eq!(in_root, id);
+        assert_eq!(
+            todo_id(&root.join("src/main.rs"), &todo, Some(Path::new("."))),
+            id
+        );
+    }
+
+    #[test]
+    fn count_duplicates() {
+        let mut todos = vec![
+            Todo::new(1, "TODO", "item"),
+            Todo::new(2, "FIXME", "item"),
+            Todo::new(3, "TODO", "item"),
+        ];
+        count_occurrences(&mut todos);
+
+        let occurrences: Vec<usize> = todos.iter().map(|t| t.occurrence).collect();
+        assert_eq!(occurrences, vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        let mut todo = Todo::new(2, "TODO", "item @u1");
+        todo.add_continuation(3, "continued");
+        todo.blame = Some(Blame {
+            author: "Lavi Blumberg".to_string(),
+            author_email: "lavifb@gmail.com".to_string(),
+            commit: "1a2b3c4d".to_string(),
+            date: "2019-01-11T00:00:00Z".to_string(),
+        });
+
+        let parsed: Todo = serde_json::from_str(&todo.to_json().unwrap()).unwrap();
+        assert_eq!(parsed, todo);
+    }
+
     #[test]
     fn json_todos() {
         let mut tf = TodoFile::new(Path::new("tests/test.rs"));
@@ -20,6 +20,14 @@ fn todor() -> Command {
     cmd
 }
 
+/// todor command that can take piped input using `write_stdin()`.
+fn todor_piped() -> assert_cmd::Command {
+    let mut cmd = assert_cmd::Command::cargo_bin("todor").unwrap();
+    cmd.current_dir(format!("tests{0}inputs", dir_sep()));
+    cmd.arg("--no-style");
+    cmd
+}
+
 #[test]
 fn basic() {
     todor()
@@ -113,7 +121,41 @@ fn py_extension() {
 
 #[test]
 fn dir_todos() {
-    todor().arg("..").assert().success().stdout("").stderr("");
+    let dir = std::env::temp_dir().join(format!("todor-dirs-{}", std::process::id()));
+    fs::create_dir_all(dir.join("src").join("nested")).unwrap();
+    fs::create_dir_all(dir.join("tests")).unwrap();
+    fs::write(dir.join("src").join(".todorignore"), "ignored.rs\n").unwrap();
+    fs::write(dir.join("src").join("ignored.rs"), "// TODO: ignored\n").unwrap();
+    fs::write(dir.join("src").join("lib.rs"), "// TODO: lib\n").unwrap();
+    fs::write(
+        dir.join("src").join("nested").join("mod.rs"),
+        "// TODO: nested\n",
+    )
+    .unwrap();
+    fs::write(dir.join("tests").join("skip.rs"), "// TODO: skip\n").unwrap();
+    fs::write(dir.join("main.rs"), "// TODO: main\n").unwrap();
+
+    todor()
+        .current_dir(&dir)
+        .arg("main.rs")
+        .arg("src/")
+        .arg("tests")
+        .arg("-i")
+        .arg("skip.rs")
+        .assert()
+        .success()
+        .stdout(
+            "main.rs
+  line 1      TODO   main
+src/lib.rs
+  line 1      TODO   lib
+src/nested/mod.rs
+  line 1      TODO   nested
+",
+        )
+        .stderr("");
+
+    fs::remove_dir_all(&dir).unwrap();
 }
 
 #[test]
@@ -180,6 +222,44 @@ fn config2yaml() {
         .stderr("");
 }
 
+#[test]
+fn config3_trailing() {
+    todor()
+        .arg("test2.py")
+        .arg("-c")
+        .arg("config3.toml")
+        .assert()
+        .success()
+        .stdout(
+            "test2.py
+  line 2      TODO   docstring comment
+  line 3      TODO   does not count
+  line 4      TODO   item\n",
+        )
+        .stderr("");
+}
+
+#[test]
+fn config3_trailing_strings() {
+    todor_piped()
+        .arg("-e")
+        .arg("rs")
+        .arg("-c")
+        .arg("config3.toml")
+        .write_stdin(
+            "let s = \"// TODO: not a comment\"; // TODO: item\n\
+             let r = r#\"\" // TODO: raw\"#; let c = '\"'; // TODO: item2\n",
+        )
+        .assert()
+        .success()
+        .stdout(
+            "
+  line 1      TODO   item
+  line 2      TODO   item2\n",
+        )
+        .stderr("");
+}
+
 #[test]
 fn multiple() {
     todor()
@@ -285,6 +365,67 @@ fn walk3() {
         .stderr("");
 }
 
+#[test]
+fn walk_threads() {
+    let expected = format!(
+        "inputs{0}test1.rs
+  line 2      TODO   item
+inputs{0}test2.py
+  line 2      TODO   docstring comment
+  line 4      TODO   item
+inputt{0}test1.rs
+  line 1      TODO   item2\n",
+        dir_sep()
+    );
+
+    for threads in &["1", "4"] {
+        todor()
+            .current_dir("tests")
+            .arg("-j")
+            .arg(threads)
+            .assert()
+            .success()
+            .stdout(expected.clone())
+            .stderr("");
+    }
+}
+
+#[test]
+fn walk_threads_parent_roots() {
+    todor()
+        .arg("-j")
+        .arg("4")
+        .arg("-T")
+        .arg("foo")
+        .assert()
+        .success()
+        .stdout(format!(
+            "test1.rs
+  line 4      FOO    bar
+..{0}inputt{0}test1.rs
+  line 3      FOO    bar2\n",
+            dir_sep()
+        ))
+        .stderr("");
+}
+
+#[test]
+fn walk_threads_invalid() {
+    todor().arg("-j").arg("many").assert().failure();
+}
+
+#[test]
+fn walk_users() {
+    todor()
+        .current_dir("tests")
+        .arg("-u")
+        .arg("user1")
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+}
+
 #[test]
 fn check1() {
     todor().arg("--check").assert().failure();
@@ -375,7 +516,7 @@ fn json() {
         .arg("json")
         .assert()
         .success()
-        .stdout(r#"[{"file":"test1.rs","line":2,"tag":"TODO","text":"item","users":[]}]"#)
+        .stdout(r#"[{"file":"test1.rs","id":"686ba6e13039","line":2,"tag":"TODO","text":"item","users":[]}]"#)
         .stderr("");
 }
 
@@ -389,7 +530,7 @@ fn json_check1() {
         .arg("--check")
         .assert()
         .failure()
-        .stdout(r#"[{"file":"test1.rs","line":2,"tag":"TODO","text":"item","users":[]}]"#)
+        .stdout(r#"[{"file":"test1.rs","id":"686ba6e13039","line":2,"tag":"TODO","text":"item","users":[]}]"#)
         .stderr("");
 }
 
@@ -408,3 +549,1373 @@ fn json_check2() {
         .stdout("[]")
         .stderr("");
 }
+
+#[test]
+fn sarif() {
+    let dir = std::env::temp_dir().join(format!("todor-sarif-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("with space.rs"), "// TODO: item\n// FIXME: bug\n").unwrap();
+    fs::write(
+        dir.join("config.json"),
+        r#"{"severity": {"fixme": "error"}}"#,
+    )
+    .unwrap();
+
+    let output = todor()
+        .current_dir(&dir)
+        .arg("with space.rs")
+        .arg("-c")
+        .arg("config.json")
+        .arg("-f")
+        .arg("sarif")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let log: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(log["version"], "2.1.0");
+    let run = &log["runs"][0];
+    assert_eq!(run["tool"]["driver"]["name"], "todor");
+
+    let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0]["id"], "FIXME");
+    assert_eq!(rules[0]["defaultConfiguration"]["level"], "error");
+    assert_eq!(rules[1]["id"], "TODO");
+    assert_eq!(rules[1]["defaultConfiguration"]["level"], "warning");
+
+    let results = run["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[1]["ruleId"], "FIXME");
+    assert_eq!(results[1]["ruleIndex"], 0);
+    assert_eq!(results[1]["level"], "error");
+    assert_eq!(results[1]["message"]["text"], "bug");
+    let location = &results[1]["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "with%20space.rs");
+    assert_eq!(location["region"]["startLine"], 2);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn github_annotations() {
+    let dir = std::env::temp_dir().join(format!("todor-github-{}", std::process::id()));
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(
+        dir.join("src").join("a,b.rs"),
+        "// TODO: 100% done\n// FIXME: bug\n//   on two lines\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("config.json"),
+        r#"{"severity": {"fixme": "error"}}"#,
+    )
+    .unwrap();
+
+    todor()
+        .current_dir(&dir)
+        .arg("src")
+        .arg("-c")
+        .arg("config.json")
+        .arg("-f")
+        .arg("github")
+        .assert()
+        .success()
+        .stdout(
+            "::warning file=src/a%2Cb.rs,line=1,endLine=1,title=TODO::100%25 done
+::error file=src/a%2Cb.rs,line=2,endLine=3,title=FIXME::bug on two lines
+",
+        )
+        .stderr("");
+
+    // paths are relative to the workspace root when run from a subdirectory
+    fs::write(dir.join(".todor"), "").unwrap();
+    todor()
+        .current_dir(dir.join("src"))
+        .arg("-c")
+        .arg("../config.json")
+        .arg("-f")
+        .arg("github")
+        .assert()
+        .success()
+        .stdout(
+            "::warning file=src/a%2Cb.rs,line=1,endLine=1,title=TODO::100%25 done
+::error file=src/a%2Cb.rs,line=2,endLine=3,title=FIXME::bug on two lines
+",
+        );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn gitlab_code_quality() {
+    todor()
+        .current_dir("tests/inputs")
+        .arg("test1.rs")
+        .arg("-f")
+        .arg("gitlab")
+        .assert()
+        .success()
+        .stdout(
+            r#"[
+  {
+    "check_name": "TODO",
+    "description": "TODO: item",
+    "fingerprint": "686ba6e13039",
+    "location": {
+      "lines": {
+        "begin": 2,
+        "end": 2
+      },
+      "path": "inputs/test1.rs"
+    },
+    "severity": "minor"
+  }
+]
+"#,
+        )
+        .stderr("");
+}
+
+/// Creates a temp directory with a file of TODOs that need escaping in XML
+/// and a config that makes FIXMEs errors.
+fn xml_test_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("todor-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("test.rs"),
+        "// TODO: check <T> & \"quotes\"\n// FIXME: it's broken\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("config.json"),
+        r#"{"severity": {"fixme": "error"}}"#,
+    )
+    .unwrap();
+    dir
+}
+
+#[test]
+fn junit() {
+    let dir = xml_test_dir("junit");
+    todor()
+        .current_dir(&dir)
+        .arg("test.rs")
+        .arg("-c")
+        .arg("config.json")
+        .arg("-f")
+        .arg("junit")
+        .assert()
+        .success()
+        .stdout(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="todor" tests="2" failures="1" skipped="1">
+  <testsuite name="test.rs" tests="2" failures="1" skipped="1">
+    <testcase name="TODO: check &lt;T&gt; &amp; &quot;quotes&quot;" classname="test.rs" file="test.rs" line="1">
+      <skipped message="check &lt;T&gt; &amp; &quot;quotes&quot;"/>
+    </testcase>
+    <testcase name="FIXME: it&apos;s broken" classname="test.rs" file="test.rs" line="2">
+      <failure type="FIXME" message="it&apos;s broken">test.rs:2: FIXME it&apos;s broken</failure>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#,
+        )
+        .stderr("");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn checkstyle() {
+    let dir = xml_test_dir("checkstyle");
+    todor()
+        .current_dir(&dir)
+        .arg("test.rs")
+        .arg("-c")
+        .arg("config.json")
+        .arg("-f")
+        .arg("checkstyle")
+        .assert()
+        .success()
+        .stdout(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<checkstyle version="4.3">
+  <file name="test.rs">
+    <error line="1" severity="warning" message="check &lt;T&gt; &amp; &quot;quotes&quot;" source="todor.TODO"/>
+    <error line="2" severity="error" message="it&apos;s broken" source="todor.FIXME"/>
+  </file>
+</checkstyle>
+"#,
+        )
+        .stderr("");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Creates a temp directory with a file of TODOs that need quoting in csv and tsv.
+fn table_test_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("todor-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("test.rs"),
+        "// TODO: check, \"quotes\"\n// FIXME(ann): tab\there\n",
+    )
+    .unwrap();
+    dir
+}
+
+#[test]
+fn csv() {
+    let dir = table_test_dir("csv");
+    todor()
+        .current_dir(&dir)
+        .arg("test.rs")
+        .arg("-f")
+        .arg("csv")
+        .assert()
+        .success()
+        .stdout(
+            "file,line,tag,text,id\r\n\
+             test.rs,1,TODO,\"check, \"\"quotes\"\"\",e2daab882d0c\r\n\
+             test.rs,2,FIXME,@ann tab\there,2b71d0bb191b\r\n",
+        )
+        .stderr("");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn tsv_columns() {
+    let dir = table_test_dir("tsv");
+    todor()
+        .current_dir(&dir)
+        .arg("test.rs")
+        .arg("-f")
+        .arg("tsv")
+        .arg("--columns")
+        .arg("tag,users,text,line,file")
+        .assert()
+        .success()
+        .stdout(
+            "tag\tusers\ttext\tline\tfile\n\
+             TODO\t\tcheck, \"quotes\"\t1\ttest.rs\n\
+             FIXME\t@ann\t@ann tab\\there\t2\ttest.rs\n",
+        )
+        .stderr("");
+
+    todor()
+        .current_dir(&dir)
+        .arg("test.rs")
+        .arg("-f")
+        .arg("tsv")
+        .arg("--columns")
+        .arg("tag,bogus")
+        .assert()
+        .failure()
+        .stderr(
+            "[todor ERROR]: 'bogus' is an invalid column. Columns are file, line, end_line, tag, \
+             text, users, id, author, author_email, commit and date\n",
+        );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn template() {
+    let dir = table_test_dir("template");
+    todor()
+        .current_dir(&dir)
+        .arg("test.rs")
+        .arg("-f")
+        .arg("template")
+        .arg("--template")
+        .arg("{file}:{line}:1: [{tag}] {text|json}{?users} ({users}){/users}")
+        .assert()
+        .success()
+        .stdout(
+            "test.rs:1:1: [TODO] check, \\\"quotes\\\"\n\
+             test.rs:2:1: [FIXME] @ann tab\\there (@ann)\n",
+        )
+        .stderr("");
+
+    todor()
+        .current_dir(&dir)
+        .arg("test.rs")
+        .arg("-f")
+        .arg("template")
+        .arg("--template")
+        .arg("{text")
+        .assert()
+        .failure()
+        .stderr("[todor ERROR]: invalid template: '{text' is not closed\n");
+
+    // the template format needs a template
+    let output = todor()
+        .current_dir(&dir)
+        .arg("test.rs")
+        .arg("-f")
+        .arg("template")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--template <TEMPLATE>"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn named_template() {
+    let dir = table_test_dir("named-template");
+    fs::write(
+        dir.join(".todor"),
+        r#"{"formats": {"chat": {
+            "header": "{count} TODOs",
+            "file_header": "*{file}* ({count})",
+            "todo": "- {tag|lower}: {text}{!users} (unassigned){/users}",
+            "file_footer": "---"
+        }}}"#,
+    )
+    .unwrap();
+
+    todor()
+        .current_dir(&dir)
+        .arg("-f")
+        .arg("template")
+        .arg("--template")
+        .arg("chat")
+        .assert()
+        .success()
+        .stdout(
+            "2 TODOs\n\
+             *test.rs* (2)\n\
+             - todo: check, \"quotes\" (unassigned)\n\
+             - fixme: @ann tab\there\n\
+             ---\n",
+        )
+        .stderr("");
+
+    todor()
+        .current_dir(&dir)
+        .arg("-f")
+        .arg("template")
+        .arg("--template")
+        .arg("vimgrep")
+        .assert()
+        .failure()
+        .stderr(
+            "[todor ERROR]: invalid template: no template named 'vimgrep' in the formats config\n",
+        );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn continuation_json() {
+    todor_piped()
+        .arg("-e")
+        .arg("rs")
+        .arg("-f")
+        .arg("json")
+        .write_stdin("// TODO: refactor this\n//   because the cache is shared\nfn main() {}\n")
+        .assert()
+        .success()
+        .stdout(
+            r#"[{"file":"","id":"80533c5cf25a","line":1,"end_line":2,"tag":"TODO","text":"refactor this because the cache is shared","users":[]}]"#,
+        )
+        .stderr("");
+}
+
+#[test]
+fn continuation_markdown() {
+    todor_piped()
+        .arg("-e")
+        .arg("rs")
+        .arg("-f")
+        .arg("markdown")
+        .write_stdin("/* TODO: refactor this\n *   because the cache is shared */\n")
+        .assert()
+        .success()
+        .stdout(
+            "### TODOs
+| Filename | line | TODO | id |
+|:---|:---:|:---|:---|
+|  | 1 | refactor this because the cache is shared | 80533c5cf25a |
+
+",
+        )
+        .stderr("");
+}
+
+#[test]
+fn cache() {
+    let workspace = std::env::temp_dir().join(format!("todor-cache-{}", std::process::id()));
+    fs::create_dir_all(&workspace).unwrap();
+    fs::write(workspace.join(".todor"), "").unwrap();
+    let test_file = workspace.join("test.rs");
+    fs::write(&test_file, "// TODO: item1\n").unwrap();
+    // modified time must be before the cache is saved for the cache to be trusted
+    let past = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+    fs::File::options()
+        .write(true)
+        .open(&test_file)
+        .unwrap()
+        .set_modified(past)
+        .unwrap();
+
+    let run_cached = || {
+        todor()
+            .current_dir(&workspace)
+            .arg("--cache")
+            .env("RUST_LOG", "todo_r=debug")
+            .output()
+            .unwrap()
+    };
+
+    let output = run_cached();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "test.rs\n  line 1      TODO   item1\n"
+    );
+    assert!(workspace.join(".todor-cache").is_file());
+
+    let output = run_cached();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "test.rs\n  line 1      TODO   item1\n"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("using cached TODOs"));
+
+    // same size so only the content hash can tell it changed
+    fs::write(&test_file, "// TODO: item2\n").unwrap();
+    let output = run_cached();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "test.rs\n  line 1      TODO   item2\n"
+    );
+
+    todor()
+        .current_dir(&workspace)
+        .arg("--no-cache")
+        .arg("-T")
+        .arg("foo")
+        .assert()
+        .success()
+        .stdout("");
+
+    fs::remove_dir_all(&workspace).unwrap();
+}
+
+#[test]
+fn watch_json_events() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    let workspace = std::env::temp_dir().join(format!("todor-watch-{}", std::process::id()));
+    fs::create_dir_all(&workspace).unwrap();
+    fs::write(workspace.join(".todor"), "").unwrap();
+    fs::write(workspace.join("test.rs"), "// TODO: item1\n").unwrap();
+
+    let mut child = todor()
+        .current_dir(&workspace)
+        .arg("--watch")
+        .arg("-f")
+        .arg("json")
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let (tx, rx) = channel();
+    let stdout = child.stdout.take().unwrap();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            if tx.send(line.unwrap()).is_err() {
+                break;
+            }
+        }
+    });
+    let next_line = || rx.recv_timeout(Duration::from_secs(10)).unwrap();
+
+    assert_eq!(
+        next_line(),
+        r#"{"event":"add","file":"test.rs","id":"a5c9b5e9f54c","line":1,"tag":"TODO","text":"item1","users":[]}"#
+    );
+
+    fs::write(workspace.join("test.rs"), "// TODO: item2\n").unwrap();
+    assert_eq!(
+        next_line(),
+        r#"{"event":"remove","file":"test.rs","id":"a5c9b5e9f54c","line":1,"tag":"TODO","text":"item1","users":[]}"#
+    );
+    assert_eq!(
+        next_line(),
+        r#"{"event":"add","file":"test.rs","id":"5bda52df6b5b","line":1,"tag":"TODO","text":"item2","users":[]}"#
+    );
+
+    fs::write(workspace.join("new.py"), "# FIXME: new file\n").unwrap();
+    assert_eq!(
+        next_line(),
+        r#"{"event":"add","file":"new.py","id":"23eab27f0a9d","line":1,"tag":"FIXME","text":"new file","users":[]}"#
+    );
+
+    fs::remove_file(workspace.join("test.rs")).unwrap();
+    assert_eq!(
+        next_line(),
+        r#"{"event":"remove","file":"test.rs","id":"5bda52df6b5b","line":1,"tag":"TODO","text":"item2","users":[]}"#
+    );
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+    fs::remove_dir_all(&workspace).unwrap();
+}
+
+#[test]
+fn watch_needs_workspace_search() {
+    let workspace =
+        std::env::temp_dir().join(format!("todor-watch-invalid-{}", std::process::id()));
+    fs::create_dir_all(&workspace).unwrap();
+    fs::write(workspace.join(".todor"), "").unwrap();
+    fs::write(workspace.join("test.rs"), "// TODO: item\n").unwrap();
+
+    todor()
+        .current_dir(&workspace)
+        .arg("--watch")
+        .arg("test.rs")
+        .assert()
+        .failure();
+    todor()
+        .current_dir(&workspace)
+        .arg("--watch")
+        .arg("baseline")
+        .arg("write")
+        .assert()
+        .failure()
+        .stderr("[todor ERROR]: --watch cannot be used with the `baseline` subcommand\n");
+    todor()
+        .current_dir(&workspace)
+        .arg("--watch")
+        .arg("rm")
+        .arg("0123")
+        .assert()
+        .failure();
+    assert_eq!(
+        fs::read_to_string(workspace.join("test.rs")).unwrap(),
+        "// TODO: item\n"
+    );
+
+    fs::remove_dir_all(&workspace).unwrap();
+}
+
+/// Runs git in repo as a test author.
+fn git(repo: &Path, args: &[&str]) {
+    git_at(repo, "2019-01-11T00:00:00+00:00", args);
+}
+
+/// Runs git in repo as a test author with commits made at date.
+fn git_at(repo: &Path, date: &str, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo)
+        .env("GIT_AUTHOR_NAME", "Test Author")
+        .env("GIT_AUTHOR_EMAIL", "author@example.com")
+        .env("GIT_AUTHOR_DATE", date)
+        .env("GIT_COMMITTER_NAME", "Test Author")
+        .env("GIT_COMMITTER_EMAIL", "author@example.com")
+        .env("GIT_COMMITTER_DATE", date)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+/// Creates a git repo in a temp directory with test.rs committed by a test author.
+fn git_test_repo(name: &str) -> std::path::PathBuf {
+    let repo = std::env::temp_dir().join(format!("todor-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&repo).unwrap();
+    fs::write(repo.join("test.rs"), "fn main() {}\n// TODO: item\n").unwrap();
+
+    git(&repo, &["init", "-q"]);
+    git(&repo, &["add", "test.rs"]);
+    git(&repo, &["commit", "-q", "-m", "add test.rs"]);
+
+    repo
+}
+
+#[test]
+fn blame_json() {
+    let repo = git_test_repo("blame-json");
+    let output = todor()
+        .current_dir(&repo)
+        .arg("--blame")
+        .arg("-f")
+        .arg("json")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.starts_with(
+        r#"[{"file":"test.rs","id":"ff64d13fe333","line":2,"tag":"TODO","text":"item","users":[],"blame":{"author":"Test Author","author_email":"author@example.com","commit":""#
+    ));
+    assert!(stdout.ends_with(r#"","date":"2019-01-11T00:00:00+00:00"}}]"#));
+
+    fs::remove_dir_all(&repo).unwrap();
+}
+
+#[test]
+fn blame_default() {
+    let repo = git_test_repo("blame-default");
+    let output = todor().current_dir(&repo).arg("--blame").output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.starts_with("test.rs\n  line 2      TODO   item  (Test Author, 2019-01-11, "));
+
+    fs::remove_dir_all(&repo).unwrap();
+}
+
+#[test]
+fn since() {
+    let repo = git_test_repo("since");
+    fs::write(
+        repo.join("test.rs"),
+        "fn main() {}\n// TODO: item\n// TODO: new item\n",
+    )
+    .unwrap();
+    fs::write(repo.join("new.py"), "# TODO: untracked\n").unwrap();
+
+    todor()
+        .current_dir(&repo)
+        .arg("--since")
+        .arg("HEAD")
+        .arg("--check")
+        .assert()
+        .failure()
+        .stdout(
+            "new.py
+  line 1      TODO   untracked
+test.rs
+  line 3      TODO   new item\n",
+        );
+
+    // nothing is staged yet
+    todor()
+        .current_dir(&repo)
+        .arg("--since")
+        .arg("HEAD")
+        .arg("--staged")
+        .arg("--check")
+        .assert()
+        .success()
+        .stdout("");
+
+    git(&repo, &["add", "test.rs"]);
+    todor()
+        .current_dir(&repo)
+        .arg("--since")
+        .arg("HEAD")
+        .arg("--staged")
+        .assert()
+        .success()
+        .stdout("test.rs\n  line 3      TODO   new item\n");
+
+    fs::remove_dir_all(&repo).unwrap();
+}
+
+#[test]
+fn since_git_config() {
+    let repo = git_test_repo("since-config");
+    git(&repo, &["config", "diff.noprefix", "true"]);
+    fs::create_dir_all(repo.join("sub")).unwrap();
+    fs::write(repo.join("sub/sub.rs"), "fn sub() {}\n").unwrap();
+    git(&repo, &["add", "sub/sub.rs"]);
+    git(&repo, &["commit", "-q", "-m", "sub"]);
+
+    fs::write(repo.join("sub/sub.rs"), "fn sub() {}\n// TODO: new item\n").unwrap();
+    fs::write(repo.join("new é.py"), "# TODO: untracked\n").unwrap();
+
+    todor()
+        .current_dir(repo.join("sub"))
+        .arg("--since")
+        .arg("HEAD")
+        .assert()
+        .success()
+        .stdout(
+            "sub.rs
+  line 2      TODO   new item
+../new é.py
+  line 1      TODO   untracked\n",
+        );
+
+    fs::remove_dir_all(&repo).unwrap();
+}
+
+#[test]
+fn tracked_only() {
+    let sub = git_test_repo("tracked-only-sub");
+    let repo = git_test_repo("tracked-only");
+    fs::write(repo.join("untracked.rs"), "// TODO: untracked\n").unwrap();
+    fs::write(repo.join("ignored.rs"), "// TODO: ignored\n").unwrap();
+    fs::write(repo.join(".todorignore"), "ignored.rs\n").unwrap();
+    git(&repo, &["add", "ignored.rs", ".todorignore"]);
+    git(
+        &repo,
+        &[
+            "-c",
+            "protocol.file.allow=always",
+            "submodule",
+            "add",
+            "-q",
+            sub.to_str().unwrap(),
+            "sub",
+        ],
+    );
+
+    todor()
+        .current_dir(&repo)
+        .arg("--tracked-only")
+        .assert()
+        .success()
+        .stdout("test.rs\n  line 2      TODO   item\n");
+
+    todor()
+        .current_dir(&repo)
+        .arg("--tracked-only")
+        .arg("--recurse-submodules")
+        .arg("-i")
+        .arg("/test.rs")
+        .assert()
+        .success()
+        .stdout("sub/test.rs\n  line 2      TODO   item\n");
+
+    fs::remove_dir_all(&repo).unwrap();
+    fs::remove_dir_all(&sub).unwrap();
+}
+
+#[test]
+fn workspace_roots() {
+    let workspace = std::env::temp_dir().join(format!("todor-roots-{}", std::process::id()));
+    let repo = workspace.join("repo");
+    fs::create_dir_all(repo.join(".jj")).unwrap();
+    fs::create_dir_all(repo.join("src")).unwrap();
+    fs::create_dir_all(workspace.join("other")).unwrap();
+    fs::write(repo.join("top.rs"), "// TODO: top\n").unwrap();
+    fs::write(repo.join("src").join("lib.rs"), "// TODO: lib\n").unwrap();
+    fs::write(workspace.join("other").join("other.rs"), "// TODO: other\n").unwrap();
+
+    // .jj marks the root
+    todor()
+        .current_dir(repo.join("src"))
+        .assert()
+        .success()
+        .stdout("lib.rs\n  line 1      TODO   lib\n../top.rs\n  line 1      TODO   top\n");
+
+    todor()
+        .current_dir(repo.join("src"))
+        .arg("--relative-to-root")
+        .assert()
+        .success()
+        .stdout("src/lib.rs\n  line 1      TODO   lib\ntop.rs\n  line 1      TODO   top\n");
+
+    todor()
+        .current_dir(repo.join("src"))
+        .arg("--root")
+        .arg("../../other")
+        .assert()
+        .success()
+        .stdout("../../other/other.rs\n  line 1      TODO   other\n");
+
+    // markers from the config replace the default ones
+    let config = workspace.join("config.json");
+    fs::write(&config, r#"{"root_markers": [".svn"]}"#).unwrap();
+    todor()
+        .current_dir(repo.join("src"))
+        .arg("-c")
+        .arg(&config)
+        .assert()
+        .failure();
+
+    fs::remove_dir_all(&workspace).unwrap();
+}
+
+#[test]
+fn staged() {
+    let repo = git_test_repo("staged");
+    fs::write(repo.join("test.rs"), "// FIXME: staged\n").unwrap();
+    git(&repo, &["add", "test.rs"]);
+    fs::write(repo.join("test.rs"), "// TODO: unstaged\n").unwrap();
+
+    todor()
+        .current_dir(&repo)
+        .arg("--staged")
+        .assert()
+        .success()
+        .stdout("test.rs\n  line 1      FIXME  staged\n");
+
+    // rm edits the working tree, which can differ from the index
+    let output = todor()
+        .current_dir(&repo)
+        .arg("--staged")
+        .arg("-f")
+        .arg("json")
+        .output()
+        .unwrap();
+    let todos: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    fs::write(
+        repo.join("test.rs"),
+        "// TODO: unstaged\n// FIXME: staged\n",
+    )
+    .unwrap();
+    todor()
+        .current_dir(&repo)
+        .arg("--staged")
+        .arg("rm")
+        .arg(todos[0]["id"].as_str().unwrap())
+        .assert()
+        .failure();
+    assert_eq!(
+        fs::read_to_string(repo.join("test.rs")).unwrap(),
+        "// TODO: unstaged\n// FIXME: staged\n"
+    );
+
+    fs::remove_dir_all(&repo).unwrap();
+}
+
+#[test]
+fn hook_install() {
+    let repo = git_test_repo("hook-install");
+    todor()
+        .current_dir(&repo)
+        .arg("hook")
+        .arg("install")
+        .arg("-t")
+        .arg("fixme")
+        .assert()
+        .success();
+
+    let hook = repo.join(".git").join("hooks").join("pre-commit");
+    assert_eq!(
+        fs::read_to_string(&hook).unwrap(),
+        "#!/bin/sh
+# Written by `todor hook install`. Blocks commits that have TODOs in the staged files.
+exec todor --staged --check -T 'fixme'
+"
+    );
+
+    // existing hooks are only replaced with --force
+    todor()
+        .current_dir(&repo)
+        .arg("hook")
+        .arg("install")
+        .assert()
+        .failure();
+    todor()
+        .current_dir(&repo)
+        .arg("hook")
+        .arg("install")
+        .arg("--force")
+        .arg("--new-only")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&hook).unwrap(),
+        "#!/bin/sh
+# Written by `todor hook install`. Blocks commits that have TODOs in the staged files.
+if git rev-parse --verify -q HEAD >/dev/null; then
+    exec todor --staged --check --since HEAD
+fi
+exec todor --staged --check
+"
+    );
+
+    fs::remove_dir_all(&repo).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn hook_blocks_commit() {
+    let repo = git_test_repo("hook-commit");
+    todor()
+        .current_dir(&repo)
+        .arg("hook")
+        .arg("install")
+        .arg("-t")
+        .arg("fixme")
+        .assert()
+        .success();
+
+    // the hook runs the todor on the PATH
+    let bin_dir = assert_cmd::cargo::cargo_bin("todor")
+        .parent()
+        .unwrap()
+        .to_path_buf();
+    let path = format!(
+        "{}:{}",
+        bin_dir.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let commit = |message: &str| {
+        Command::new("git")
+            .args(["commit", "-q", "-m", message])
+            .current_dir(&repo)
+            .env("PATH", &path)
+            .env("GIT_AUTHOR_NAME", "Test Author")
+            .env("GIT_AUTHOR_EMAIL", "author@example.com")
+            .env("GIT_COMMITTER_NAME", "Test Author")
+            .env("GIT_COMMITTER_EMAIL", "author@example.com")
+            .output()
+            .unwrap()
+    };
+
+    fs::write(repo.join("new.rs"), "// TODO: allowed\n").unwrap();
+    git(&repo, &["add", "new.rs"]);
+    assert!(commit("add todo").status.success());
+
+    fs::write(repo.join("new.rs"), "// FIXME: blocked\n").unwrap();
+    git(&repo, &["add", "new.rs"]);
+    let output = commit("add fixme");
+    assert!(!output.status.success());
+    // git shows the output of hooks on stderr
+    assert!(String::from_utf8_lossy(&output.stderr).contains("blocked"));
+
+    fs::remove_dir_all(&repo).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn hook_new_only_first_commit() {
+    let repo = std::env::temp_dir().join(format!("todor-hook-first-{}", std::process::id()));
+    fs::create_dir_all(&repo).unwrap();
+    git(&repo, &["init", "-q"]);
+    todor()
+        .current_dir(&repo)
+        .arg("hook")
+        .arg("install")
+        .arg("--new-only")
+        .assert()
+        .success();
+
+    let bin_dir = assert_cmd::cargo::cargo_bin("todor")
+        .parent()
+        .unwrap()
+        .to_path_buf();
+    let path = format!(
+        "{}:{}",
+        bin_dir.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let commit = || {
+        Command::new("git")
+            .args(["commit", "-q", "-m", "first"])
+            .current_dir(&repo)
+            .env("PATH", &path)
+            .env("GIT_AUTHOR_NAME", "Test Author")
+            .env("GIT_AUTHOR_EMAIL", "author@example.com")
+            .env("GIT_COMMITTER_NAME", "Test Author")
+            .env("GIT_COMMITTER_EMAIL", "author@example.com")
+            .output()
+            .unwrap()
+    };
+
+    // every line of the first commit is new
+    fs::write(repo.join("test.rs"), "// TODO: first\n").unwrap();
+    git(&repo, &["add", "test.rs"]);
+    let output = commit();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("first"));
+
+    fs::write(repo.join("test.rs"), "fn main() {}\n").unwrap();
+    git(&repo, &["add", "test.rs"]);
+    assert!(commit().status.success());
+
+    fs::remove_dir_all(&repo).unwrap();
+}
+
+#[test]
+fn baseline() {
+    let workspace = std::env::temp_dir().join(format!("todor-baseline-{}", std::process::id()));
+    fs::create_dir_all(&workspace).unwrap();
+    fs::write(workspace.join(".todor"), "").unwrap();
+    fs::write(
+        workspace.join("test.rs"),
+        "// TODO: item1\n// FIXME: item2\n",
+    )
+    .unwrap();
+
+    todor()
+        .current_dir(&workspace)
+        .arg("baseline")
+        .arg("write")
+        .assert()
+        .success()
+        .stdout("");
+    assert!(workspace.join(".todor-baseline").is_file());
+
+    todor()
+        .current_dir(&workspace)
+        .arg("--baseline")
+        .arg("--check")
+        .assert()
+        .success()
+        .stdout("");
+
+    // moving a known TODO does not make it new
+    fs::write(
+        workspace.join("test.rs"),
+        "fn main() {}\n// TODO: item1\n// TODO: item3\n",
+    )
+    .unwrap();
+    todor()
+        .current_dir(&workspace)
+        .arg("--baseline")
+        .arg("--check")
+        .assert()
+        .failure()
+        .stdout("test.rs\n  line 3      TODO   item3\n");
+
+    todor()
+        .current_dir(&workspace)
+        .arg("--baseline")
+        .arg("--warn-stale")
+        .assert()
+        .success()
+        .stderr("baseline TODO no longer found: test.rs: FIXME item2\n");
+
+    fs::remove_dir_all(&workspace).unwrap();
+}
+
+#[test]
+fn ids() {
+    let workspace = std::env::temp_dir().join(format!("todor-ids-{}", std::process::id()));
+    fs::create_dir_all(&workspace).unwrap();
+    fs::write(workspace.join(".todor"), "").unwrap();
+    fs::write(
+        workspace.join("test.rs"),
+        "// TODO: item\nfn main() {}\n// TODO: item\n// FIXME: other\n",
+    )
+    .unwrap();
+
+    todor()
+        .current_dir(&workspace)
+        .arg("--ids")
+        .assert()
+        .success()
+        .stdout(
+            "test.rs
+  line 1      TODO   item  [ff64d13fe333]
+  line 3      TODO   item  [1e5f9848ee23]
+  line 4      FIXME  other  [cf658b07655f]\n",
+        );
+
+    todor()
+        .current_dir(&workspace)
+        .arg("show")
+        .arg("1e5f98")
+        .assert()
+        .success()
+        .stdout("test.rs\n  line 3      TODO   item  [1e5f9848ee23]\n");
+
+    todor()
+        .current_dir(&workspace)
+        .arg("rm")
+        .arg("ff64d13fe333")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(workspace.join("test.rs")).unwrap(),
+        "fn main() {}\n// TODO: item\n// FIXME: other"
+    );
+
+    // the remaining duplicate takes the ID of the first one
+    todor()
+        .current_dir(&workspace)
+        .arg("show")
+        .arg("ff64d13fe333")
+        .assert()
+        .success()
+        .stdout("test.rs\n  line 2      TODO   item  [ff64d13fe333]\n");
+
+    todor()
+        .current_dir(&workspace)
+        .arg("show")
+        .arg("1e5f98")
+        .assert()
+        .failure()
+        .stderr("[todor ERROR]: no TODO with ID '1e5f98'\n");
+
+    fs::remove_dir_all(&workspace).unwrap();
+}
+
+#[test]
+fn ids_from_subdirectory() {
+    let workspace = std::env::temp_dir().join(format!("todor-ids-subdir-{}", std::process::id()));
+    let sub = workspace.join("sub");
+    fs::create_dir_all(&sub).unwrap();
+    fs::write(workspace.join(".todor"), "").unwrap();
+    fs::write(sub.join("test.rs"), "fn main() {}\n// TODO: item\n").unwrap();
+
+    let id = |dir: &std::path::Path, args: &[&str]| {
+        let output = todor()
+            .current_dir(dir)
+            .args(args)
+            .arg("-f")
+            .arg("json")
+            .output()
+            .unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        report[0]["id"].as_str().unwrap().to_string()
+    };
+
+    let root_id = id(&workspace, &[]);
+    assert_eq!(id(&sub, &[]), root_id);
+    assert_eq!(id(&sub, &["--relative-to-root"]), root_id);
+    assert_eq!(id(&sub, &["test.rs"]), root_id);
+
+    todor()
+        .current_dir(&sub)
+        .arg("rm")
+        .arg(&root_id)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(sub.join("test.rs")).unwrap(),
+        "fn main() {}"
+    );
+
+    fs::remove_dir_all(&workspace).unwrap();
+}
+
+#[test]
+fn diff_reports() {
+    let dir = std::env::temp_dir().join(format!("todor-diff-{}", std::process::id()));
+    let workspace = dir.join("workspace");
+    fs::create_dir_all(&workspace).unwrap();
+    fs::write(workspace.join(".todor"), "").unwrap();
+
+    let save_report = |name: &str| {
+        let output = todor()
+            .current_dir(&workspace)
+            .arg("-f")
+            .arg("json")
+            .output()
+            .unwrap();
+        fs::write(dir.join(name), output.stdout).unwrap();
+    };
+
+    fs::write(
+        workspace.join("test.rs"),
+        "// TODO: item\n// TODO(ann): fix the parser\n// FIXME: old bug\n",
+    )
+    .unwrap();
+    save_report("old.json");
+
+    fs::write(
+        workspace.join("test.rs"),
+        "fn main() {}\n// TODO: item\n// TODO(bob): fix the parser\n// TODO: new thing\n",
+    )
+    .unwrap();
+    save_report("new.json");
+
+    todor()
+        .current_dir(&dir)
+        .arg("diff")
+        .arg("old.json")
+        .arg("new.json")
+        .assert()
+        .success()
+        .stdout(
+            "1 added, 1 resolved, 1 moved, 0 edited, 1 reassigned
+
+Added:
+  test.rs:4  TODO  new thing
+
+Resolved:
+  test.rs:3  FIXME  old bug
+
+Moved:
+  test.rs:1 -> test.rs:2
+    TODO  item
+
+Reassigned:
+  test.rs:2 -> test.rs:3
+    - TODO  @ann fix the parser
+    + TODO  @bob fix the parser
+",
+        );
+
+    todor()
+        .current_dir(&dir)
+        .arg("diff")
+        .arg("old.json")
+        .arg("new.json")
+        .arg("-f")
+        .arg("markdown")
+        .assert()
+        .success()
+        .stdout(
+            "### Added
+| Filename | line | type | content |
+|:---|:---:|:---:|:---|
+| test.rs | 4 | TODO | new thing |
+
+### Resolved
+| Filename | line | type | content |
+|:---|:---:|:---:|:---|
+| test.rs | 3 | FIXME | old bug |
+
+### Moved
+| From | To | type | content |
+|:---|:---|:---:|:---|
+| test.rs:1 | test.rs:2 | TODO | item |
+
+### Reassigned
+| Filename | line | type | old content | new content |
+|:---|:---:|:---:|:---|:---|
+| test.rs | 3 | TODO | @ann fix the parser | @bob fix the parser |
+
+",
+        );
+
+    todor()
+        .current_dir(&dir)
+        .arg("diff")
+        .arg("old.json")
+        .arg("missing.json")
+        .assert()
+        .failure();
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn rev() {
+    let repo = git_test_repo("rev");
+    fs::create_dir_all(repo.join("src")).unwrap();
+    fs::write(repo.join("src").join("lib.rs"), "// FIXME(ann): bug\n").unwrap();
+    fs::write(repo.join(".todorignore"), "ignored.rs\n").unwrap();
+    fs::write(repo.join("ignored.rs"), "// TODO: ignored\n").unwrap();
+    // files that are not UTF-8 are skipped like when walking the working tree
+    fs::write(repo.join("latin1.rs"), b"// TODO: caf\xe9\n").unwrap();
+    git(&repo, &["add", "-A"]);
+    git(&repo, &["commit", "-q", "-m", "add lib.rs"]);
+    git(&repo, &["tag", "v1"]);
+
+    // the working tree is not searched
+    fs::write(repo.join("test.rs"), "// TODO: uncommitted\n").unwrap();
+
+    todor()
+        .current_dir(&repo)
+        .arg("--rev")
+        .arg("v1")
+        .assert()
+        .success()
+        .stdout(
+            "src/lib.rs
+  line 1      FIXME  @ann bug
+test.rs
+  line 2      TODO   item
+",
+        );
+
+    // paths are relative to the current directory
+    let output = todor()
+        .current_dir(repo.join("src"))
+        .arg("--rev")
+        .arg("v1")
+        .arg("-u")
+        .arg("ann")
+        .arg("-f")
+        .arg("json")
+        .output()
+        .unwrap();
+    let todos: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(todos.as_array().unwrap().len(), 1);
+    assert_eq!(todos[0]["file"], "lib.rs");
+    assert_eq!(todos[0]["text"], "@ann bug");
+
+    todor()
+        .current_dir(&repo)
+        .arg("--rev")
+        .arg("missing")
+        .assert()
+        .failure();
+
+    // rm edits the working tree, which can differ from the revision
+    let id = todos[0]["id"].as_str().unwrap();
+    fs::write(
+        repo.join("src").join("lib.rs"),
+        "// TODO: new\n// FIXME(ann): bug\n",
+    )
+    .unwrap();
+    todor()
+        .current_dir(&repo)
+        .arg("--rev")
+        .arg("v1")
+        .arg("rm")
+        .arg(id)
+        .assert()
+        .failure();
+    assert_eq!(
+        fs::read_to_string(repo.join("src").join("lib.rs")).unwrap(),
+        "// TODO: new\n// FIXME(ann): bug\n"
+    );
+
+    fs::remove_dir_all(&repo).unwrap();
+}
+
+#[test]
+fn history() {
+    let repo = git_test_repo("history");
+    let commit = |date: &str| {
+        git_at(&repo, date, &["add", "-A"]);
+        git_at(&repo, date, &["commit", "-q", "-m", "change"]);
+        git_at(&repo, date, &["rev-parse", "HEAD"])
+    };
+
+    // same week as the first commit
+    fs::write(repo.join(".todorignore"), "ignored.rs\n").unwrap();
+    fs::write(repo.join("ignored.rs"), "// TODO: ignored\n").unwrap();
+    fs::write(
+        repo.join("test.rs"),
+        "fn main() {}\n// TODO: item\n// FIXME(ann): bug\n",
+    )
+    .unwrap();
+    let week1 = commit("2019-01-12T00:00:00+00:00");
+
+    fs::write(
+        repo.join(".todor"),
+        "tags: [\"todo\", \"fixme\", \"hack\"]\n",
+    )
+    .unwrap();
+    fs::write(repo.join("test.rs"), "// HACK: @bob @bob x\n").unwrap();
+    let week2 = commit("2019-01-15T00:00:00+00:00");
+
+    todor()
+        .current_dir(&repo)
+        .arg("history")
+        .arg("--step")
+        .arg("weekly")
+        .assert()
+        .success()
+        .stdout(format!(
+            "date, commit, total, FIXME, HACK, TODO, @ann, @bob
+2019-01-12T00:00:00+00:00, {}, 2, 1, 0, 1, 1, 0
+2019-01-15T00:00:00+00:00, {}, 1, 0, 1, 0, 0, 1\n",
+            week1, week2
+        ));
+
+    let output = todor()
+        .current_dir(&repo)
+        .arg("history")
+        .arg("--since")
+        .arg("2019-01-14")
+        .arg("-f")
+        .arg("json")
+        .output()
+        .unwrap();
+    let history: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(history.as_array().unwrap().len(), 1);
+    assert_eq!(history[0]["commit"], week2.as_str());
+    assert_eq!(history[0]["tags"]["HACK"], 1);
+
+    fs::remove_dir_all(&repo).unwrap();
+}